@@ -0,0 +1,61 @@
+use crate::interpreter::InterpreterError;
+use crate::lexer::Token;
+use crate::parser::Value;
+use num_bigint::BigInt;
+use serde::Serializer;
+
+// Arithmetic for the big integer mode, which never overflows.
+// Results that fit into an i32 stay a Value::Integer, so equality and the
+// builtins keep working on small numbers.
+
+pub fn to_big(value: &Value) -> Result<BigInt, InterpreterError> {
+    match value {
+        Value::Integer(int) => Ok(BigInt::from(*int)),
+        Value::BigInteger(int) => Ok(int.clone()),
+        _ => Err(InterpreterError::TypeError(format!("Expected an integer, found {:?}", value)))
+    }
+}
+
+pub fn from_big(int: BigInt) -> Value {
+    match i32::try_from(&int) {
+        Ok(small) => Value::Integer(small),
+        Err(_) => Value::BigInteger(int)
+    }
+}
+
+pub fn binary(token: &Token, left: &Value, right: &Value) -> Result<Value, InterpreterError> {
+    let left = to_big(left)?;
+    let right = to_big(right)?;
+    Ok(from_big(match token {
+        Token::Plus => left + right,
+        Token::Minus => left - right,
+        Token::Multiply => left * right,
+        Token::Divide => {
+            if right == BigInt::from(0) {
+                return Err(InterpreterError::DivisionByZero);
+            }
+            left / right
+        },
+        Token::Modulo => {
+            if right == BigInt::from(0) {
+                return Err(InterpreterError::DivisionByZero);
+            }
+            left % right
+        },
+        _ => panic!("Invalid BinaryOp Token: {:?}", token)
+    }))
+}
+
+pub fn unary(token: &Token, value: &Value) -> Result<Value, InterpreterError> {
+    let value = to_big(value)?;
+    Ok(from_big(match token {
+        Token::Plus => value,
+        Token::Minus => -value,
+        _ => panic!("Invalid UnaryOp Token")
+    }))
+}
+
+// JSON numbers can't be trusted beyond 64 bits, so big integers are written as digits in a string.
+pub fn serialize<S: Serializer>(int: &BigInt, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(int)
+}
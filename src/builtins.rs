@@ -0,0 +1,694 @@
+use crate::interpreter::InterpreterError;
+use crate::parser::Value;
+
+pub type Builtin = fn(&[Value]) -> Result<Value, InterpreterError>;
+
+// Functions the interpreter handles itself, as they need more than their arguments.
+pub const INTERPRETER_FUNCTIONS: &[&str] = &["d;D", "args", "zeig_umgebung", "zaehler", "tiefe", "fakultaet", "potenz", "frage_weiter", "umgebung", "aufrufe"];
+
+// Whether calling name works without declaring a function first.
+pub fn is_builtin(name: &str) -> bool {
+    name.starts_with(":O__") || INTERPRETER_FUNCTIONS.contains(&name) || lookup(name).is_some()
+}
+
+// Built-in functions, which only need their evaluated arguments.
+// Their names are reserved and can't be used for user-defined functions.
+pub fn lookup(name: &str) -> Option<Builtin> {
+    Some(match name {
+        "hol" => hol,
+        "summe" => summe,
+        "produkt" => produkt,
+        "fehler" => fehler,
+        "umdreh" => umdreh,
+        "pad" => pad,
+        "klemm" => klemm,
+        "teile" => teile,
+        "code" => code,
+        "zeichen_von" => zeichen_von,
+        "enthaelt" => enthaelt,
+        "finde" => finde,
+        "kuerzer" => kuerzer,
+        "laenger" => laenger,
+        "laenge" => laenge,
+        "gross" => gross,
+        "klein" => klein,
+        "kleinstes" => kleinstes,
+        "groesstes" => groesstes,
+        "spalte" => spalte,
+        "bereich" => bereich,
+        "abstand" => abstand,
+        "schablone" => schablone,
+        "sortiere" => sortiere,
+        "nimm" => nimm,
+        "ueberspring" => ueberspring,
+        "locker_gleich" => locker_gleich,
+        "fuelle" => fuelle,
+        "ggt" => ggt,
+        "kgv" => kgv,
+        "trimme" => trimme,
+        "trimme_links" => trimme_links,
+        "trimme_rechts" => trimme_rechts,
+        "ersetze" => ersetze,
+        "faengt_an" => faengt_an,
+        "endet_mit" => endet_mit,
+        "enthaelt_text" => enthaelt_text,
+        "liste_zu_text" => liste_zu_text,
+        "text_zu_liste" => text_zu_liste,
+        "dauer" => dauer,
+        "zu_zahl" => zu_zahl,
+        "zu_wahrheit" => zu_wahrheit,
+        _ => return None
+    })
+}
+
+fn expect_count(name: &str, arguments: &[Value], count: usize) -> Result<(), InterpreterError> {
+    if arguments.len() != count {
+        return Err(InterpreterError::ArgumentCountMismatch {
+            function: name.to_string(),
+            expected: count,
+            found: arguments.len()
+        });
+    }
+    Ok(())
+}
+
+fn expect_count_between(name: &str, arguments: &[Value], min: usize, max: usize) -> Result<(), InterpreterError> {
+    if arguments.len() < min {
+        return expect_count(name, arguments, min);
+    }
+    if arguments.len() > max {
+        return expect_count(name, arguments, max);
+    }
+    Ok(())
+}
+
+fn expect_string<'a>(name: &str, value: &'a Value) -> Result<&'a String, InterpreterError> {
+    match value {
+        Value::String(string) => Ok(string),
+        _ => Err(InterpreterError::TypeError(format!("{} expects a string, found {:?}", name, value)))
+    }
+}
+
+fn expect_integer(name: &str, value: &Value) -> Result<i32, InterpreterError> {
+    match value {
+        Value::Integer(int) => Ok(*int),
+        _ => Err(InterpreterError::TypeError(format!("{} expects an integer, found {:?}", name, value)))
+    }
+}
+
+fn expect_boolean(name: &str, value: &Value) -> Result<bool, InterpreterError> {
+    match value {
+        Value::Boolean(b) => Ok(*b),
+        _ => Err(InterpreterError::TypeError(format!("{} expects a boolean, found {:?}", name, value)))
+    }
+}
+
+fn expect_list<'a>(name: &str, value: &'a Value) -> Result<&'a Vec<Value>, InterpreterError> {
+    match value {
+        Value::List(values) => Ok(values),
+        _ => Err(InterpreterError::TypeError(format!("{} expects a list, found {:?}", name, value)))
+    }
+}
+
+// Maps a possibly negative index (counting from the end) into 0..length.
+pub fn resolve_index(index: i32, length: usize) -> Result<usize, InterpreterError> {
+    let resolved = if index < 0 { length as i64 + index as i64 } else { index as i64 };
+    if resolved < 0 || resolved >= length as i64 {
+        return Err(InterpreterError::IndexOutOfRange { index, length });
+    }
+    Ok(resolved as usize)
+}
+
+// hol(list, index)
+fn hol(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("hol", arguments, 2)?;
+    let values = expect_list("hol", &arguments[0])?;
+    let index = expect_integer("hol", &arguments[1])?;
+    Ok(values[resolve_index(index, values.len())?].clone())
+}
+
+// Integers stay integers, once a float shows up the rest is folded as floats.
+fn fold_numbers(name: &str, arguments: &[Value], start: i32, integers: fn(i32, i32) -> Option<i32>, floats: fn(f64, f64) -> f64) -> Result<Value, InterpreterError> {
+    expect_count(name, arguments, 1)?;
+    let mut result = Value::Integer(start);
+    for value in expect_list(name, &arguments[0])? {
+        result = match (result, value) {
+            (Value::Integer(left), Value::Integer(right)) => Value::Integer(integers(left, *right).ok_or(InterpreterError::IntegerOverflow)?),
+            (Value::Integer(left), Value::Float(right)) => Value::Float(floats(f64::from(left), *right)),
+            (Value::Float(left), Value::Integer(right)) => Value::Float(floats(left, f64::from(*right))),
+            (Value::Float(left), Value::Float(right)) => Value::Float(floats(left, *right)),
+            _ => return Err(InterpreterError::TypeError(format!("{} expects a number, found {:?}", name, value)))
+        };
+    }
+    Ok(result)
+}
+
+// summe(list)
+fn summe(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    fold_numbers("summe", arguments, 0, i32::checked_add, |left, right| left + right)
+}
+
+// produkt(list)
+fn produkt(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    fold_numbers("produkt", arguments, 1, i32::checked_mul, |left, right| left * right)
+}
+
+// fehler(message)
+fn fehler(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("fehler", arguments, 1)?;
+    Err(InterpreterError::UserError(arguments[0].to_string()))
+}
+
+// umdreh(string | list)
+fn umdreh(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("umdreh", arguments, 1)?;
+    match &arguments[0] {
+        Value::String(string) => Ok(Value::String(string.chars().rev().collect())),
+        Value::List(values) => Ok(Value::List(values.iter().rev().cloned().collect())),
+        value => Err(InterpreterError::TypeError(format!("umdreh expects a string or list, found {:?}", value)))
+    }
+}
+
+// pad(n, width) pads with zeros after the sign, pad(n, width, <c>) pads with c in front.
+fn pad(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count_between("pad", arguments, 2, 3)?;
+    let number = expect_integer("pad", &arguments[0])?;
+    let width = expect_integer("pad", &arguments[1])?;
+    if width < 0 {
+        return Err(InterpreterError::InvalidArgument(format!("pad width must not be negative, found {}", width)));
+    }
+    let width = width as usize;
+    match arguments.get(2) {
+        None => Ok(Value::String(format!("{:0width$}", number, width = width))),
+        Some(fill) => {
+            let fill = expect_string("pad", fill)?;
+            let mut fill_chars = fill.chars();
+            match (fill_chars.next(), fill_chars.next()) {
+                (Some(fill), None) => {
+                    let digits = number.to_string();
+                    let padding = width.saturating_sub(digits.chars().count());
+                    Ok(Value::String(std::iter::repeat_n(fill, padding).chain(digits.chars()).collect()))
+                },
+                _ => Err(InterpreterError::InvalidArgument(format!("pad expects a single fill character, found <{}>", fill)))
+            }
+        }
+    }
+}
+
+// klemm(x, min, max)
+fn klemm(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("klemm", arguments, 3)?;
+    let x = expect_integer("klemm", &arguments[0])?;
+    let min = expect_integer("klemm", &arguments[1])?;
+    let max = expect_integer("klemm", &arguments[2])?;
+    if min > max {
+        return Err(InterpreterError::InvalidArgument(format!("klemm: min {} is greater than max {}", min, max)));
+    }
+    Ok(Value::Integer(x.clamp(min, max)))
+}
+
+// teile(s) splits on whitespace, teile(s, separator) on the separator.
+fn teile(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count_between("teile", arguments, 1, 2)?;
+    let string = expect_string("teile", &arguments[0])?;
+    let pieces: Vec<Value> = match arguments.get(1) {
+        None => string.split_whitespace().map(|piece| Value::String(piece.to_string())).collect(),
+        Some(separator) => {
+            let separator = expect_string("teile", separator)?;
+            if separator.is_empty() {
+                return Err(InterpreterError::InvalidArgument("teile: separator must not be empty".to_string()));
+            }
+            string.split(separator.as_str()).map(|piece| Value::String(piece.to_string())).collect()
+        }
+    };
+    Ok(Value::List(pieces))
+}
+
+// code(s) is the codepoint of the first character of s.
+fn code(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("code", arguments, 1)?;
+    let string = expect_string("code", &arguments[0])?;
+    match string.chars().next() {
+        Some(c) => Ok(Value::Integer(c as i32)),
+        None => Err(InterpreterError::InvalidArgument("code expects a non-empty string".to_string()))
+    }
+}
+
+// zeichen_von(n) is the character with codepoint n.
+fn zeichen_von(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("zeichen_von", arguments, 1)?;
+    let codepoint = expect_integer("zeichen_von", &arguments[0])?;
+    match u32::try_from(codepoint).ok().and_then(char::from_u32) {
+        Some(c) => Ok(Value::String(c.to_string())),
+        None => Err(InterpreterError::InvalidArgument(format!("zeichen_von: {} is not a valid codepoint", codepoint)))
+    }
+}
+
+// enthaelt(list, value)
+fn enthaelt(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("enthaelt", arguments, 2)?;
+    let values = expect_list("enthaelt", &arguments[0])?;
+    Ok(Value::Boolean(values.iter().any(|value| value.equals(&arguments[1]))))
+}
+
+// finde(list, value) is the index of the first equal element, - if there is none.
+fn finde(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("finde", arguments, 2)?;
+    let values = expect_list("finde", &arguments[0])?;
+    Ok(match values.iter().position(|value| value.equals(&arguments[1])) {
+        Some(index) => Value::Integer(index as i32),
+        None => Value::None
+    })
+}
+
+fn compare_lengths(name: &str, arguments: &[Value], expected: std::cmp::Ordering) -> Result<Value, InterpreterError> {
+    expect_count(name, arguments, 2)?;
+    let left = expect_string(name, &arguments[0])?.chars().count();
+    let right = expect_string(name, &arguments[1])?.chars().count();
+    Ok(Value::Boolean(left.cmp(&right) == expected))
+}
+
+// kuerzer(a, b) is :) if a has fewer characters than b.
+fn kuerzer(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    compare_lengths("kuerzer", arguments, std::cmp::Ordering::Less)
+}
+
+// laenge(s) counts the characters, not the bytes.
+fn laenge(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("laenge", arguments, 1)?;
+    let length = expect_string("laenge", &arguments[0])?.chars().count();
+    Ok(Value::Integer(i32::try_from(length).map_err(|_| InterpreterError::IntegerOverflow)?))
+}
+
+// laenger(a, b) is :) if a has more characters than b.
+fn laenger(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    compare_lengths("laenger", arguments, std::cmp::Ordering::Greater)
+}
+
+// gross(s)
+fn gross(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("gross", arguments, 1)?;
+    Ok(Value::String(expect_string("gross", &arguments[0])?.to_uppercase()))
+}
+
+// klein(s)
+fn klein(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("klein", arguments, 1)?;
+    Ok(Value::String(expect_string("klein", &arguments[0])?.to_lowercase()))
+}
+
+// trimme(s) drops the whitespace at both ends.
+fn trimme(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("trimme", arguments, 1)?;
+    Ok(Value::String(expect_string("trimme", &arguments[0])?.trim().to_string()))
+}
+
+// trimme_links(s)
+fn trimme_links(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("trimme_links", arguments, 1)?;
+    Ok(Value::String(expect_string("trimme_links", &arguments[0])?.trim_start().to_string()))
+}
+
+// trimme_rechts(s)
+fn trimme_rechts(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("trimme_rechts", arguments, 1)?;
+    Ok(Value::String(expect_string("trimme_rechts", &arguments[0])?.trim_end().to_string()))
+}
+
+// ersetze(s, alt, neu) replaces every occurrence of alt, left to right.
+fn ersetze(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("ersetze", arguments, 3)?;
+    let string = expect_string("ersetze", &arguments[0])?;
+    let old = expect_string("ersetze", &arguments[1])?;
+    let new = expect_string("ersetze", &arguments[2])?;
+    if old.is_empty() {
+        return Err(InterpreterError::InvalidArgument("ersetze cannot replace an empty string".to_string()));
+    }
+    Ok(Value::String(string.replace(old.as_str(), new)))
+}
+
+// Picks the element that stays ahead in the given ordering, using kleina/krasser semantics.
+fn extreme(name: &str, arguments: &[Value], keep: std::cmp::Ordering) -> Result<Value, InterpreterError> {
+    expect_count(name, arguments, 1)?;
+    let values = expect_list(name, &arguments[0])?;
+    let mut best = values.first().ok_or_else(|| InterpreterError::InvalidArgument(format!("{} expects a non-empty list", name)))?;
+    for value in &values[1..] {
+        match value.ordering(best) {
+            Some(ordering) if ordering == keep => best = value,
+            Some(_) => {},
+            None => return Err(InterpreterError::TypeError(format!("{} cannot order {:?} and {:?}", name, value, best)))
+        }
+    }
+    if best.ordering(best).is_none() {
+        return Err(InterpreterError::TypeError(format!("{} expects integers or strings, found {:?}", name, best)));
+    }
+    Ok(best.clone())
+}
+
+// kleinstes(list)
+fn kleinstes(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    extreme("kleinstes", arguments, std::cmp::Ordering::Less)
+}
+
+// groesstes(list)
+fn groesstes(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    extreme("groesstes", arguments, std::cmp::Ordering::Greater)
+}
+
+// spalte(value, width) left-aligns the displayed value in a field of width characters,
+// spalte(value, width, <rechts>) right-aligns it. Longer values are not cut.
+fn spalte(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count_between("spalte", arguments, 2, 3)?;
+    let text = arguments[0].to_string();
+    let width = expect_integer("spalte", &arguments[1])?;
+    if width < 0 {
+        return Err(InterpreterError::InvalidArgument(format!("spalte width must not be negative, found {}", width)));
+    }
+    let padding = " ".repeat((width as usize).saturating_sub(text.chars().count()));
+    let alignment = match arguments.get(2) {
+        Some(alignment) => expect_string("spalte", alignment)?.as_str(),
+        None => "links"
+    };
+    match alignment {
+        "links" => Ok(Value::String(text + &padding)),
+        "rechts" => Ok(Value::String(padding + &text)),
+        _ => Err(InterpreterError::InvalidArgument(format!("spalte aligns <links> or <rechts>, found <{}>", alignment)))
+    }
+}
+
+// bereich(start, end) counts from start up to end (exclusive),
+// bereich(start, end, step) in steps, counting down for negative steps.
+fn bereich(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count_between("bereich", arguments, 2, 3)?;
+    let start = expect_integer("bereich", &arguments[0])?;
+    let end = expect_integer("bereich", &arguments[1])?;
+    let step = match arguments.get(2) {
+        Some(step) => expect_integer("bereich", step)?,
+        None => 1
+    };
+    if step == 0 {
+        return Err(InterpreterError::InvalidArgument("bereich step must not be 0".to_string()));
+    }
+    let mut values = Vec::new();
+    let mut current = start as i64;
+    while (step > 0 && current < end as i64) || (step < 0 && current > end as i64) {
+        values.push(Value::Integer(current as i32));
+        current += step as i64;
+    }
+    Ok(Value::List(values))
+}
+
+// abstand(a, b) is |a - b|, which may not fit into an integer for far apart a and b.
+fn abstand(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("abstand", arguments, 2)?;
+    let a = expect_integer("abstand", &arguments[0])?;
+    let b = expect_integer("abstand", &arguments[1])?;
+    let distance = i32::try_from(a.abs_diff(b)).map_err(|_| InterpreterError::IntegerOverflow)?;
+    Ok(Value::Integer(distance))
+}
+
+// schablone(template, values...) puts the values into the {} placeholders in order.
+fn schablone(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count_between("schablone", arguments, 1, usize::MAX)?;
+    let template = expect_string("schablone", &arguments[0])?;
+    let pieces: Vec<&str> = template.split("{}").collect();
+    let placeholders = pieces.len() - 1;
+    let values = arguments.len() - 1;
+    if placeholders != values {
+        return Err(InterpreterError::InvalidArgument(format!("schablone has {} placeholders but {} values", placeholders, values)));
+    }
+    let mut result = String::from(pieces[0]);
+    for (value, piece) in arguments[1..].iter().zip(&pieces[1..]) {
+        result.push_str(&value.to_string());
+        result.push_str(piece);
+    }
+    Ok(Value::String(result))
+}
+
+// sortiere(list) sorts integers by size and strings lexicographically, like kleina.
+fn sortiere(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("sortiere", arguments, 1)?;
+    let mut values = expect_list("sortiere", &arguments[0])?.clone();
+    if let Some(first) = values.first() {
+        for value in &values {
+            if value.ordering(first).is_none() {
+                return Err(InterpreterError::TypeError(format!("sortiere cannot order {:?} and {:?}", first, value)));
+            }
+        }
+    }
+    values.sort_by(|left, right| left.ordering(right).expect("Checked to be orderable."));
+    Ok(Value::List(values))
+}
+
+// Splits the list after its first n elements, n is capped at the length.
+fn split_list<'a>(name: &str, arguments: &'a [Value]) -> Result<(&'a [Value], &'a [Value]), InterpreterError> {
+    expect_count(name, arguments, 2)?;
+    let values = expect_list(name, &arguments[0])?;
+    let n = expect_integer(name, &arguments[1])?;
+    if n < 0 {
+        return Err(InterpreterError::InvalidArgument(format!("{} count must not be negative, found {}", name, n)));
+    }
+    Ok(values.split_at((n as usize).min(values.len())))
+}
+
+// nimm(list, n) keeps the first n elements.
+fn nimm(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let (taken, _) = split_list("nimm", arguments)?;
+    Ok(Value::List(taken.to_vec()))
+}
+
+// ueberspring(list, n) drops the first n elements.
+fn ueberspring(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    let (_, rest) = split_list("ueberspring", arguments)?;
+    Ok(Value::List(rest.to_vec()))
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::String(string) => string.trim().parse().ok().filter(|number: &f64| number.is_finite()),
+        _ => value.as_float()
+    }
+}
+
+// locker_gleich(a, b) compares loosely: first as numbers if both look like one
+// (<1>, < 1 > and 1 are equal), otherwise as they would be printed (<:)> equals :)).
+fn locker_gleich(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("locker_gleich", arguments, 2)?;
+    let (a, b) = (&arguments[0], &arguments[1]);
+    Ok(Value::Boolean(match (as_number(a), as_number(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a.to_string() == b.to_string()
+    }))
+}
+
+// fuelle(s, width) repeats s and cuts it off at exactly width characters.
+fn fuelle(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("fuelle", arguments, 2)?;
+    let string = expect_string("fuelle", &arguments[0])?;
+    let width = expect_integer("fuelle", &arguments[1])?;
+    if width < 0 {
+        return Err(InterpreterError::InvalidArgument(format!("fuelle width must not be negative, found {}", width)));
+    }
+    if string.is_empty() && width > 0 {
+        return Err(InterpreterError::InvalidArgument("fuelle cannot fill with an empty string".to_string()));
+    }
+    Ok(Value::String(string.chars().cycle().take(width as usize).collect()))
+}
+
+// Euclid on the magnitudes, so i32::MIN doesn't overflow halfway through.
+fn gcd(a: i32, b: i32) -> u32 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+// ggt(a, b) is the greatest common divisor, ggt(0, 0) is 0.
+fn ggt(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("ggt", arguments, 2)?;
+    let a = expect_integer("ggt", &arguments[0])?;
+    let b = expect_integer("ggt", &arguments[1])?;
+    let divisor = i32::try_from(gcd(a, b)).map_err(|_| InterpreterError::IntegerOverflow)?;
+    Ok(Value::Integer(divisor))
+}
+
+// kgv(a, b) is the least common multiple, 0 if either is 0.
+fn kgv(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("kgv", arguments, 2)?;
+    let a = expect_integer("kgv", &arguments[0])?;
+    let b = expect_integer("kgv", &arguments[1])?;
+    if a == 0 || b == 0 {
+        return Ok(Value::Integer(0));
+    }
+    let multiple = (a.unsigned_abs() / gcd(a, b)).checked_mul(b.unsigned_abs())
+        .and_then(|multiple| i32::try_from(multiple).ok())
+        .ok_or(InterpreterError::IntegerOverflow)?;
+    Ok(Value::Integer(multiple))
+}
+
+// faengt_an(s, p), like endet_mit and enthaelt_text :) for an empty p.
+fn faengt_an(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("faengt_an", arguments, 2)?;
+    let string = expect_string("faengt_an", &arguments[0])?;
+    let prefix = expect_string("faengt_an", &arguments[1])?;
+    Ok(Value::Boolean(string.starts_with(prefix.as_str())))
+}
+
+// endet_mit(s, p)
+fn endet_mit(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("endet_mit", arguments, 2)?;
+    let string = expect_string("endet_mit", &arguments[0])?;
+    let suffix = expect_string("endet_mit", &arguments[1])?;
+    Ok(Value::Boolean(string.ends_with(suffix.as_str())))
+}
+
+// enthaelt_text(s, p), enthaelt is for lists.
+fn enthaelt_text(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("enthaelt_text", arguments, 2)?;
+    let string = expect_string("enthaelt_text", &arguments[0])?;
+    let part = expect_string("enthaelt_text", &arguments[1])?;
+    Ok(Value::Boolean(string.contains(part.as_str())))
+}
+
+// liste_zu_text(list) writes the list the way :O__ prints it, but with strings written
+// as literals, e.g. /1, /:), -\, <avo>\, so text_zu_liste can read it back.
+fn liste_zu_text(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("liste_zu_text", arguments, 1)?;
+    expect_list("liste_zu_text", &arguments[0])?;
+    let mut text = String::new();
+    write_element(&arguments[0], &mut text);
+    Ok(Value::String(text))
+}
+
+fn write_element(value: &Value, text: &mut String) {
+    match value {
+        Value::String(string) => {
+            text.push('<');
+            for c in string.chars() {
+                if matches!(c, '<' | '>' | '\\') {
+                    text.push('\\');
+                }
+                text.push(c);
+            }
+            text.push('>');
+        },
+        Value::List(values) => {
+            text.push('/');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    text.push_str(", ");
+                }
+                write_element(value, text);
+            }
+            text.push('\\');
+        },
+        value => text.push_str(&value.to_string())
+    }
+}
+
+// text_zu_liste(s) reads a list written by liste_zu_text back in.
+fn text_zu_liste(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("text_zu_liste", arguments, 1)?;
+    let text = expect_string("text_zu_liste", &arguments[0])?;
+    match read_list(text.trim()) {
+        Some((list, "")) => Ok(list),
+        Some((_, rest)) => Err(InterpreterError::ParseError(format!("text_zu_liste: unexpected {:?} after the list", rest))),
+        None => Err(InterpreterError::ParseError(format!("text_zu_liste: {:?} is not a list", text)))
+    }
+}
+
+// Reads one list from the start of text, returning it and the text after it.
+fn read_list(text: &str) -> Option<(Value, &str)> {
+    let mut rest = text.strip_prefix('/')?;
+    let mut elements = Vec::new();
+    if let Some(after) = rest.strip_prefix('\\') {
+        return Some((Value::List(elements), after));
+    }
+    loop {
+        let (element, after) = if rest.starts_with('/') {
+            read_list(rest)?
+        } else if rest.starts_with('<') {
+            read_string(rest)?
+        } else {
+            let end = [rest.find(", "), rest.find('\\')].into_iter().flatten().min()?;
+            (read_element(&rest[..end])?, &rest[end..])
+        };
+        elements.push(element);
+        match after.strip_prefix(", ") {
+            Some(after) => rest = after,
+            None => return after.strip_prefix('\\').map(|after| (Value::List(elements), after))
+        }
+    }
+}
+
+// Reads a string literal from the start of text, with the escapes the lexer knows.
+fn read_string(text: &str) -> Option<(Value, &str)> {
+    let rest = text.strip_prefix('<')?;
+    let mut string = String::new();
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '>' => return Some((Value::String(string), &rest[i + 1..])),
+            '\\' => match chars.next()? {
+                (_, escaped @ ('<' | '>' | '\\')) => string.push(escaped),
+                (_, 'n') => string.push('\n'),
+                (_, 't') => string.push('\t'),
+                (_, other) => {
+                    string.push('\\');
+                    string.push(other);
+                }
+            },
+            c => string.push(c)
+        }
+    }
+    None
+}
+
+// A number, smiley or -, strings come as literals.
+fn read_element(text: &str) -> Option<Value> {
+    match text {
+        ":)" => Some(Value::Boolean(true)),
+        ":(" => Some(Value::Boolean(false)),
+        "-" => Some(Value::None),
+        _ => match (text.parse::<i32>(), text.parse::<f64>()) {
+            (Ok(int), _) => Some(Value::Integer(int)),
+            (_, Ok(float)) if float.is_finite() => Some(Value::Float(float)),
+            _ => None
+        }
+    }
+}
+
+// dauer(ms) writes a duration in milliseconds readable, e.g. 90000 is <1m 30s>.
+fn dauer(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("dauer", arguments, 1)?;
+    let milliseconds = expect_integer("dauer", &arguments[0])?;
+    if milliseconds < 0 {
+        return Err(InterpreterError::InvalidArgument(format!("dauer: {} is negative", milliseconds)));
+    }
+    if milliseconds == 0 {
+        return Ok(Value::String("0s".to_string()));
+    }
+    let units = [("h", 3_600_000), ("m", 60_000), ("s", 1000), ("ms", 1)];
+    let mut rest = milliseconds;
+    let mut parts = Vec::new();
+    for (unit, size) in units {
+        if rest >= size {
+            parts.push(format!("{}{}", rest / size, unit));
+            rest %= size;
+        }
+    }
+    Ok(Value::String(parts.join(" ")))
+}
+
+// zu_zahl(b) is 1 for :) and 0 for :(, handy for counting.
+fn zu_zahl(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("zu_zahl", arguments, 1)?;
+    Ok(Value::Integer(i32::from(expect_boolean("zu_zahl", &arguments[0])?)))
+}
+
+// zu_wahrheit(n) is :( for 0 and :) for everything else.
+fn zu_wahrheit(arguments: &[Value]) -> Result<Value, InterpreterError> {
+    expect_count("zu_wahrheit", arguments, 1)?;
+    Ok(Value::Boolean(expect_integer("zu_wahrheit", &arguments[0])? != 0))
+}
@@ -0,0 +1,179 @@
+use crate::builtins;
+use crate::lexer::{Lexer, Position};
+use crate::parser::{ASTNode, Parser};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt::Display;
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticKind {
+    Error,
+    Warning
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub message: String,
+    pub position: Position
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let kind = match self.kind {
+            DiagnosticKind::Error => "error",
+            DiagnosticKind::Warning => "warning"
+        };
+        write!(formatter, "{} at {}:{}: {}", kind, self.position.line, self.position.column, self.message)
+    }
+}
+
+// Parses the program and checks it, a syntax error is the only diagnostic then.
+pub fn check_source(text: &str) -> Vec<Diagnostic> {
+    let mut parser = Parser::new(Lexer::new(text));
+    match parser.parse() {
+        Ok(tree) => check(&tree, text),
+        Err(e) => vec![Diagnostic {
+            kind: DiagnosticKind::Error,
+            message: format!("{:?}", e),
+            position: Position::from_offset(text, parser.position())
+        }]
+    }
+}
+
+// Looks for suspicious code without running the program.
+// The text is the source the tree was parsed from, the diagnostics point into it.
+pub fn check(tree: &ASTNode, text: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    undeclared_functions(tree, text, &mut diagnostics);
+    reserved_function_names(tree, text, &mut diagnostics);
+    unused_variables(tree, true, text, &mut diagnostics);
+    diagnostics
+}
+
+// Calls to functions that are neither built in nor declared anywhere in the program.
+fn undeclared_functions(tree: &ASTNode, text: &str, diagnostics: &mut Vec<Diagnostic>) {
+    let mut declared: HashSet<&String> = HashSet::new();
+    let mut called: Vec<(&String, usize)> = Vec::new();
+    collect_functions(tree, &mut declared, &mut called);
+
+    let mut reported: HashSet<&String> = HashSet::new();
+    for (name, offset) in called {
+        if !declared.contains(name) && !builtins::is_builtin(name) && reported.insert(name) {
+            diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::Error,
+                message: format!("function `{}` is called but never declared", name),
+                position: Position::from_offset(text, offset)
+            });
+        }
+    }
+}
+
+// Declarations the interpreter refuses, as the built-in function of that name would always win.
+fn reserved_function_names(node: &ASTNode, text: &str, diagnostics: &mut Vec<Diagnostic>) {
+    if let ASTNode::FunctionDeclaration {name, offset, ..} = node {
+        if builtins::is_builtin(name) {
+            diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::Error,
+                message: format!("function `{}` has the name of a built-in function", name),
+                position: Position::from_offset(text, *offset)
+            });
+        }
+    }
+    for child in node.children() {
+        reserved_function_names(child, text, diagnostics);
+    }
+}
+
+fn collect_functions<'a>(node: &'a ASTNode, declared: &mut HashSet<&'a String>, called: &mut Vec<(&'a String, usize)>) {
+    match node {
+        ASTNode::FunctionDeclaration {name, parameters, ..} => {
+            declared.insert(name);
+            // Parameters and variables may hold functions written with funny(...).
+            declared.extend(parameters);
+        },
+        ASTNode::Lambda {parameters, ..} => {
+            declared.extend(parameters);
+        },
+        ASTNode::Assign {left, ..} => {
+            if let ASTNode::Variable {name, ..} = &**left {
+                declared.insert(name);
+            }
+        },
+        ASTNode::FunctionCall {function, ..} => {
+            if let ASTNode::Variable {name, offset} = &**function {
+                called.push((name, *offset));
+            }
+        },
+        _ => {}
+    }
+    for child in node.children() {
+        collect_functions(child, declared, called);
+    }
+}
+
+// Every function body is its own scope, like in the interpreter.
+// Top-level variables may also be read from any function.
+fn unused_variables(scope: &ASTNode, top_level: bool, text: &str, diagnostics: &mut Vec<Diagnostic>) {
+    let mut assigned: Vec<(&String, usize)> = Vec::new();
+    let mut read: HashSet<&String> = HashSet::new();
+    let mut functions: Vec<&ASTNode> = Vec::new();
+    collect_variables(scope, &mut assigned, &mut read, &mut functions);
+    if top_level {
+        for function in &functions {
+            collect_reads(function, &mut read);
+        }
+    }
+
+    let mut reported: HashSet<&String> = HashSet::new();
+    for (name, offset) in assigned {
+        if !read.contains(name) && reported.insert(name) {
+            diagnostics.push(Diagnostic {
+                kind: DiagnosticKind::Warning,
+                message: format!("variable `{}` is assigned but never read", name),
+                position: Position::from_offset(text, offset)
+            });
+        }
+    }
+    for function in functions {
+        unused_variables(function, false, text, diagnostics);
+    }
+}
+
+fn collect_reads<'a>(node: &'a ASTNode, read: &mut HashSet<&'a String>) {
+    if let ASTNode::Variable {name, ..} = node {
+        read.insert(name);
+    }
+    for child in node.children() {
+        collect_reads(child, read);
+    }
+}
+
+fn collect_variables<'a>(node: &'a ASTNode, assigned: &mut Vec<(&'a String, usize)>, read: &mut HashSet<&'a String>, functions: &mut Vec<&'a ASTNode>) {
+    match node {
+        ASTNode::Assign {left, right} => {
+            if let ASTNode::Variable {name, offset} = &**left {
+                assigned.push((name, *offset));
+            }
+            collect_variables(right, assigned, read, functions);
+        },
+        ASTNode::Variable {name, ..} => {
+            read.insert(name);
+        },
+        ASTNode::FunctionCall {function, parameters} => {
+            collect_variables(function, assigned, read, functions);
+            for parameter in parameters {
+                collect_variables(parameter, assigned, read, functions);
+            }
+        },
+        ASTNode::FunctionDeclaration {execution_block, ..} | ASTNode::Lambda {execution_block, ..} => {
+            functions.push(execution_block);
+        },
+        _ => {
+            for child in node.children() {
+                collect_variables(child, assigned, read, functions);
+            }
+        }
+    }
+}
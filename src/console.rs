@@ -0,0 +1,157 @@
+use crate::interpreter::{Scope, InterpreterError};
+use crate::lexer::Lexer;
+use crate::session::Session;
+use crate::parser::{ASTNode, Value, Parser};
+use std::io::{BufRead, Write};
+
+// Where the program and the humanoids read from and write to.
+pub struct Console {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    // Where :O__err writes, so diagnostics stay out of the regular output.
+    error_output: Box<dyn Write>,
+    // Writes every line read back to the output, which makes piped sessions readable.
+    echo_input: bool,
+    session: Session,
+    // Bytes the program may write to output and error output together.
+    // Writes that don't fit are dropped, the interpreter then stops with OutputLimitExceeded.
+    output_limit: Option<usize>,
+    written: usize,
+    output_limit_exceeded: bool
+}
+
+impl Console {
+    pub fn new(input: Box<dyn BufRead>, output: Box<dyn Write>, error_output: Box<dyn Write>, echo_input: bool, session: Session, output_limit: Option<usize>) -> Self {
+        Console {
+            input,
+            output,
+            error_output,
+            echo_input,
+            session,
+            output_limit,
+            written: 0,
+            output_limit_exceeded: false
+        }
+    }
+
+    // Counts the bytes against the output limit, false if they don't fit anymore.
+    fn admit(&mut self, bytes: usize) -> bool {
+        if let Some(limit) = self.output_limit {
+            if self.output_limit_exceeded || self.written + bytes > limit {
+                self.output_limit_exceeded = true;
+                return false;
+            }
+        }
+        self.written += bytes;
+        true
+    }
+
+    pub fn check_output_limit(&self) -> Result<(), InterpreterError> {
+        match self.output_limit {
+            Some(limit) if self.output_limit_exceeded => Err(InterpreterError::OutputLimitExceeded(limit)),
+            _ => Ok(())
+        }
+    }
+
+    // Lets everything through again, so the interpreter can still tell why it stopped.
+    pub fn lift_output_limit(&mut self) {
+        self.output_limit = None;
+    }
+
+    pub fn read_line(&mut self, text: &str) -> Option<String> {
+        write!(self, "{}", text).expect("IO error.");
+        self.flush().expect("IO error.");
+        let input = &mut self.input;
+        let line = self.session.input(|| {
+            let mut buffer = String::new();
+            input.read_line(&mut buffer).ok().map(|_| buffer)
+        });
+        if let Some(buffer) = &line {
+            if self.echo_input {
+                writeln!(self, "{}", buffer.trim_end_matches(['\r', '\n'])).expect("IO error.");
+            }
+        }
+        line
+    }
+
+    pub fn write_error(&mut self, text: &str) {
+        // Flushed first, so the streams stay in order when both go to the same terminal.
+        self.output.flush().expect("IO error.");
+        if !self.admit(text.len() + 1) {
+            return;
+        }
+        writeln!(self.error_output, "{}", text).expect("IO error.");
+        self.error_output.flush().expect("IO error.");
+    }
+
+    pub fn read_value(&mut self, text: &str) -> Value {
+        match self.read_line(text) {
+            Some(buffer) => {
+                parse_value(&buffer)
+            },
+            None => {Value::None}
+        }
+    }
+
+    // Like read_value, but a variable name in the answer stands for its value,
+    // looked up like the program would.
+    pub fn read_answer(&mut self, text: &str, scope: &Scope, globals: &Scope) -> Value {
+        match self.read_line(text) {
+            Some(buffer) => {
+                parse_answer(&buffer, scope, globals)
+            },
+            None => {Value::None}
+        }
+    }
+}
+
+impl Write for Console {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if !self.admit(buf.len()) {
+            // Swallowed, failing here would only panic in the writeln!s.
+            return Ok(buf.len());
+        }
+        self.output.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.output.flush()
+    }
+}
+
+// Parses a literal typed in by the user, anything else becomes Value::None.
+pub fn parse_value(buffer: &str) -> Value {
+    let mut new_parser = Parser::new(Lexer::new_fill_greeting_farewell(buffer));
+    let node = new_parser.parse();
+    match node {
+        Ok(ASTNode::Block{children}) => {
+            match children.first() {
+                Some(ASTNode::Assign{left:_, right: answer}) => {
+                    literal(answer).unwrap_or(Value::None)
+                },
+                _ => {Value::None}
+            }
+        }
+        _ => {
+            Value::None
+        }
+    }
+}
+
+// A value or a list of them, as written in the answer.
+fn literal(node: &ASTNode) -> Option<Value> {
+    match node {
+        ASTNode::Value {value} => Some(value.clone()),
+        ASTNode::List {elements} => elements.iter().map(literal).collect::<Option<Vec<_>>>().map(Value::List),
+        _ => None
+    }
+}
+
+// Parses a literal or the name of a variable in scope, anything else becomes Value::None.
+pub fn parse_answer(buffer: &str, scope: &Scope, globals: &Scope) -> Value {
+    match scope.variable(globals, buffer.trim()) {
+        Some(value) => value.clone(),
+        None => parse_value(buffer)
+    }
+}
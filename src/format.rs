@@ -0,0 +1,53 @@
+use crate::parser::Value;
+
+// How numbers look when the output functions print them.
+// The default is plain and locale-independent: no separators, shortest float digits.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NumberFormat {
+    // Digits after the decimal point for floats.
+    pub precision: Option<usize>,
+    // Put between groups of three digits, e.g. '.' for 1.000.000.
+    pub thousands_separator: Option<char>
+}
+
+impl NumberFormat {
+    pub fn format(&self, value: &Value) -> String {
+        match value {
+            Value::Integer(int) => self.group_thousands(&int.to_string()),
+            Value::Float(float) => {
+                let text = match self.precision {
+                    Some(precision) => format!("{:.*}", precision, float),
+                    None => value.to_string()
+                };
+                match text.split_once('.') {
+                    Some((whole, fraction)) => format!("{}.{}", self.group_thousands(whole), fraction),
+                    None => self.group_thousands(&text)
+                }
+            },
+            Value::List(values) => {
+                let elements: Vec<String> = values.iter().map(|value| self.format(value)).collect();
+                format!("/{}\\", elements.join(", "))
+            },
+            _ => value.to_string()
+        }
+    }
+
+    fn group_thousands(&self, number: &str) -> String {
+        let separator = match self.thousands_separator {
+            Some(separator) => separator,
+            None => return number.to_string()
+        };
+        let (sign, digits) = match number.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", number)
+        };
+        let mut grouped = String::from(sign);
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                grouped.push(separator);
+            }
+            grouped.push(digit);
+        }
+        grouped
+    }
+}
@@ -1,8 +1,12 @@
 use rand::Rng;
-use crate::parser::{ASTNode, Value, Parser};
+use rand::rngs::StdRng;
+use crate::console::Console;
+use crate::parser::{ASTNode, Value};
 use crate::interpreter::{Scope, InterpreterError};
+use crate::session::Session;
+use std::collections::BTreeMap;
 use std::io::prelude::*;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 #[derive(PartialEq, Clone, Debug)]
 pub enum Mood {
@@ -28,6 +32,61 @@ impl std::fmt::Display for Mood {
     }
 }
 
+// The language the humanoids talk in.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum Language {
+    #[default]
+    Deutsch,
+    English
+}
+
+impl Language {
+    pub fn pick(&self, deutsch: &'static str, english: &'static str) -> &'static str {
+        match self {
+            Language::Deutsch => deutsch,
+            Language::English => english
+        }
+    }
+}
+
+// How long humanoids take their time, not at all when delays are switched off.
+pub fn delay(delays: bool, duration: Duration) -> Duration {
+    if delays { duration } else { Duration::ZERO }
+}
+
+fn pause(session: &Session, delays: bool, duration: Duration) {
+    let duration = session.sleep(delay(delays, duration));
+    if !duration.is_zero() {
+        std::thread::sleep(duration);
+    }
+}
+
+// Randomly uppercases characters, the louder the more.
+fn raise_voice(rng: &mut StdRng, shout_level: usize, text: &str) -> String {
+    let mut s = String::new();
+    for c in text.chars() {
+        let r = rng.gen_range(0..100);
+        if ((shout_level-1)*10) > r {
+            for upper_c in c.to_uppercase() {
+                s.push(upper_c);
+            }
+        } else {
+            s.push(c);
+        }
+    }
+    s
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HumanoidSettings {
+    // Humanoids stay out of the way.
+    pub strict_work: bool,
+    // Humanoids still keep track of their stress in strict work.
+    pub silent_stats: bool,
+    pub language: Language,
+    pub delays: bool
+}
+
 pub trait Humanoid {
     fn mood_level(&self) -> u32;
     fn mood_changed(&mut self) -> bool;
@@ -40,107 +99,120 @@ pub struct Worker {
     user_answer: Option<Value>,
     question_cooldown: Instant,
     cooldown: u128,
-    strict_work: bool,
+    settings: HumanoidSettings,
+    rng: StdRng,
+    session: Session,
 }
 
 pub struct Shouter {
     voice_damage: u32,
-    strict_work: bool
-}
-
-pub fn read_line(text: &str) -> Option<String> {
-    let mut buffer = String::new();
-    print!("{}", text);
-    std::io::stdout().flush().expect("IO error.");
-    match std::io::stdin().read_line(&mut buffer) {
-        Ok(_) => {
-            Some(buffer)
-        },
-        Err(_) => {None}
-    }
-}
-
-pub fn read_value(text: &str) -> Value {
-    match read_line(text) {
-        Some(buffer) => {
-            let mut new_parser = Parser::new(crate::Lexer::new_fill_greeting_farewell(&buffer));
-            let node = new_parser.parse();
-            match node {
-                Ok(ASTNode::Block{children}) => {
-                    match children.get(0) {
-                        Some(ASTNode::Assign{left:_, right: answer}) => {
-                            match &**answer {
-                                ASTNode::Value {value: answer} => {
-                                    answer.clone()
-                                },
-                                _ => {Value::None}
-                            }
-                        },
-                        _ => {Value::None}
-                    }
-                } 
-                _ => {
-                    Value::None
-                }
-            }
-        },
-        None => {Value::None}
-    }
+    settings: HumanoidSettings,
+    rng: StdRng,
+    session: Session,
 }
 
 impl Worker {
-    pub fn new(strict_work: bool) -> Self {
+    pub fn new(settings: HumanoidSettings, rng: StdRng, session: Session) -> Self {
         Worker {
             prev_mood: Mood::Happy,
-            strict_work,
+            settings,
             stress_level: 0,
             user_answer: None,
             question_cooldown: Instant::now(),
-            cooldown: 20
+            cooldown: 20,
+            rng,
+            session
         }
     }
 
-    pub fn call(&mut self, scope: &Scope, node: &ASTNode, correct: &Value) -> Result<(), InterpreterError>{
-        if self.strict_work {
+    pub fn stress_level(&self) -> u32 {
+        self.stress_level
+    }
+
+    // Tells about an error, in character unless in strict work.
+    pub fn describe_error(&mut self, error: &InterpreterError) -> String {
+        if self.settings.strict_work {
+            return match error {
+                InterpreterError::UserError(message) => format!("Fehler: {}", message),
+                InterpreterError::DivisionByZero => format!("Oh oh... {}", self.settings.language.pick("Durch 0 teilen geht nicht.", "Cannot divide by 0.")),
+                _ => format!("Oh oh... {:?}", error)
+            };
+        }
+        let language = self.settings.language;
+        let complaint = match error {
+            InterpreterError::TypeError(_) => language.pick("Das passt doch gar nicht zusammen!", "That doesn't fit together at all!"),
+            InterpreterError::UserError(_) => language.pick("Du wolltest es ja so!", "You asked for it!"),
+            InterpreterError::DivisionByZero => language.pick("Durch 0? Das kann ja keiner!", "By 0? Nobody can do that!"),
+            InterpreterError::DisturbedWorker => language.pick("Ich mach hier nicht mehr weiter!", "I'm not going on like this!"),
+            _ => language.pick("Da hast du mir was Kaputtes gegeben!", "You gave me something broken!")
+        };
+        let shout_level = match HumanoidControl::mood(self) {
+            Mood::Happy | Mood::Glad => 1,
+            Mood::Okay | Mood::Sad => 3,
+            Mood::Aggressive | Mood::Deactivated => 6
+        };
+        let detail = match error {
+            InterpreterError::UserError(message) => message.clone(),
+            _ => format!("{:?}", error)
+        };
+        format!("{} {} ({})", HumanoidControl::mood(self), raise_voice(&mut self.rng, shout_level, complaint), detail)
+    }
+
+    // Quizzes the user about node once worn out, answers may name variables of scope or globals.
+    pub fn call(&mut self, console: &mut Console, scope: &Scope, globals: &Scope, node: &ASTNode, correct: &Value) -> Result<(), InterpreterError>{
+        if self.settings.strict_work {
+            if self.settings.silent_stats {
+                // Average stress without rolling dice, so strict runs stay deterministic.
+                self.stress_level = self.stress_level.saturating_add(5);
+            }
             return Ok(());
         }
 
-        self.stress_level = self.stress_level + rand::thread_rng().gen_range(1..10);
-        let current_mood = HumanoidControl::mood::<Worker>(&self);
+        self.stress_level += self.rng.gen_range(1..10);
+        let current_mood = HumanoidControl::mood::<Worker>(self);
         if self.mood_changed() {
-            println!("[ {} ]", current_mood);
-            std::thread::sleep(std::time::Duration::from_millis(800));
+            writeln!(console, "[ {} ]", current_mood).expect("IO error.");
+            pause(&self.session, self.settings.delays, Duration::from_millis(800));
         }
-            if  current_mood == Mood::Deactivated && self.question_cooldown.elapsed().as_nanos() > self.cooldown {
+            if  current_mood == Mood::Deactivated && self.session.cooldown_over(|| self.question_cooldown.elapsed().as_nanos() > self.cooldown) {
                 if let ASTNode::Value{value: _} = node {
                     // Simple value evalution is boring.
                     return Ok(());
                 }
-                println!("{}, Ich kann nicht mehr... Zu was wertet dieser Ausdruck hier aus?", HumanoidControl::mood::<Worker>(&self));
-                println!("{}", "-".repeat(15));
-                println!("Symbols: {:?}", scope.symbol_table);
-                println!("{:?}", node);
-                println!("{}", "-".repeat(15));
-                self.user_answer = Some(read_value(">>"));
-                
+                if !can_be_typed(correct) {
+                    // Asking would be unfair, no answer could be right.
+                    return Ok(());
+                }
+                writeln!(console, "{}, {}", HumanoidControl::mood::<Worker>(self), self.settings.language.pick(
+                    "Ich kann nicht mehr... Zu was wertet dieser Ausdruck hier aus?",
+                    "I can't go on... What does this expression evaluate to?"
+                )).expect("IO error.");
+                writeln!(console, "{}", "-".repeat(15)).expect("IO error.");
+                // Sorted, so the same session always prints the same quiz.
+                let symbols: BTreeMap<_, _> = scope.symbol_table.iter().collect();
+                writeln!(console, "Symbols: {:?}", symbols).expect("IO error.");
+                writeln!(console, "{:?}", node).expect("IO error.");
+                writeln!(console, "{}", "-".repeat(15)).expect("IO error.");
+                self.user_answer = Some(console.read_answer(">>", scope, globals));
+
                 if let Some(answer) = &self.user_answer {
-                    if *answer == *correct {
+                    if answer.equals(correct) {
                         if *correct == Value::None {
-                            println!("Wow, gar nichts...");
+                            writeln!(console, "{}", self.settings.language.pick("Wow, gar nichts...", "Wow, nothing at all...")).expect("IO error.");
                         }
-                        println!("Danke, du hast recht!");
+                        writeln!(console, "{}", self.settings.language.pick("Danke, du hast recht!", "Thanks, you are right!")).expect("IO error.");
                         self.stress_level = 0;
-                        self.cooldown = rand::thread_rng().gen_range(1000000..1000000000);
+                        self.cooldown = self.rng.gen_range(1000000..1000000000);
                         self.question_cooldown = Instant::now();
                     } else {
-                        println!("¿Ehm, nein? Es wäre {}.", correct);
+                        writeln!(console, "{} {}.", self.settings.language.pick("¿Ehm, nein? Es wäre", "¿Ehm, no? It would be"), correct).expect("IO error.");
                         return Err(InterpreterError::DisturbedWorker);
                     }
                     self.user_answer = None;
                 }
             }
-           
-        
+
+
         Ok(())
     }
 
@@ -148,69 +220,105 @@ impl Worker {
 }
 
 impl Shouter {
-    pub fn new(strict_work: bool) -> Self {
+    pub fn new(settings: HumanoidSettings, rng: StdRng, session: Session) -> Self {
         Shouter {
             voice_damage: 0,
-            strict_work
+            settings,
+            rng,
+            session
         }
     }
 
-    pub fn shout(&mut self, shout_level: usize, text: String) {
-        if self.strict_work  {
-            println!("{}", text);
+    pub fn voice_damage(&self) -> u32 {
+        self.voice_damage
+    }
+
+    pub fn shout(&mut self, console: &mut Console, shout_level: usize, text: String) {
+        if self.settings.strict_work  {
+            if self.settings.silent_stats {
+                self.voice_damage = self.voice_damage.saturating_add(shout_level as u32);
+            }
+            writeln!(console, "{}", text).expect("IO error.");
         } else {
-        let mut rng = rand::thread_rng();
         if self.voice_damage > 1000 {
-            std::thread::sleep(std::time::Duration::from_millis(rng.gen_range(20..500)));
-            println!("{} {}", HumanoidControl::mood::<Shouter>(&self), 
-            match rng.gen_range(1..4) {
+            pause(&self.session, self.settings.delays, Duration::from_millis(self.rng.gen_range(20..500)));
+            writeln!(console, "{} {}", HumanoidControl::mood::<Shouter>(self),
+            match self.rng.gen_range(1..4) {
                 1 => {
                     "*hust*"
-                }, 
+                },
                 2 => {"*keuch*"},
                 3 => {"*arr*"},
                 _ => {"*hrrm*"}
-            });
-            if rand::thread_rng().gen_range(0..1) == 0 {
-                println!("Kann ich was zu trinken haben?");
-                match read_value("Gebe: ") {
+            }).expect("IO error.");
+            if self.rng.gen_range(0..1) == 0 {
+                writeln!(console, "{}", self.settings.language.pick("Kann ich was zu trinken haben?", "Can I have something to drink?")).expect("IO error.");
+                match console.read_value(self.settings.language.pick("Gebe: ", "Give: ")) {
                     Value::String(s) => {
                         match s.to_lowercase().as_str() {
-                            "tee"|"wasser" => {
-                                println!("Danke!");
+                            "tee"|"wasser"|"tea"|"water" => {
+                                writeln!(console, "{}", self.settings.language.pick("Danke!", "Thanks!")).expect("IO error.");
                                 self.voice_damage = 0;
                             },
                             _ => {
-                                println!("Das trinke ich nicht.");
+                                writeln!(console, "{}", self.settings.language.pick("Das trinke ich nicht.", "I don't drink that.")).expect("IO error.");
                             }
                         }
                     },
                     _ => {
-                        println!("<Du musst in meiner Sprache sprechen>");
+                        writeln!(console, "{}", self.settings.language.pick("<Du musst in meiner Sprache sprechen>", "<You have to speak my language>")).expect("IO error.");
                     }
                 };
-                std::thread::sleep(std::time::Duration::from_millis(800));
+                pause(&self.session, self.settings.delays, Duration::from_millis(800));
             }
         } else {
-            let mut s = String::new();
-            for c in text.chars() {
-                let r = rng.gen_range(0..100);
-                if ((shout_level-1)*10) > r {
-                    for upper_c in c.to_uppercase() {
-                        s.push(upper_c);
-                    }
-                } else {
-                    s.push(c);
-                }
-            }
-            println!("{}", s);
-            self.voice_damage = self.voice_damage + shout_level as u32;
-            std::thread::sleep(std::time::Duration::from_nanos(self.voice_damage as u64 * 100000));
+            let s = raise_voice(&mut self.rng, shout_level, &text);
+            writeln!(console, "{}", s).expect("IO error.");
+            self.voice_damage += shout_level as u32;
+            pause(&self.session, self.settings.delays, Duration::from_nanos(self.voice_damage as u64 * 100000));
         }
     }
 }
 }
 
+// Looks over the program once before it runs and has an opinion on it.
+pub struct Manager {
+    settings: HumanoidSettings
+}
+
+impl Manager {
+    pub fn new(settings: HumanoidSettings) -> Self {
+        Manager {
+            settings
+        }
+    }
+
+    pub fn review(&mut self, console: &mut Console, tree: &ASTNode) {
+        if self.settings.strict_work {
+            return;
+        }
+        let height = tree.height();
+        let comment = match height {
+            0..=8 => self.settings.language.pick("Schön übersichtlich, weiter so!", "Nice and tidy, keep it up!"),
+            9..=16 => self.settings.language.pick("Geht so. Ein paar Ebenen weniger wären auch okay.", "So-so. A few levels less would be fine too."),
+            _ => self.settings.language.pick("Das versteht doch keiner mehr! Wer soll das warten?", "Nobody understands this anymore! Who is going to maintain it?")
+        };
+        writeln!(console, "[ Manager | {} {} ] {}", self.settings.language.pick("Tiefe", "Depth"), height, comment).expect("IO error.");
+    }
+}
+
+// Whether the user can type the value in as an answer, functions can't be.
+fn can_be_typed(value: &Value) -> bool {
+    match value {
+        Value::List(values) => values.iter().all(can_be_typed),
+        Value::Function(_) => false,
+        // Answers are read without big integers.
+        #[cfg(feature = "bignum")]
+        Value::BigInteger(_) => false,
+        _ => true
+    }
+}
+
 pub struct HumanoidControl {}
 
 impl HumanoidControl{
@@ -244,11 +352,11 @@ impl Humanoid for Worker {
 
     fn mood_changed(&mut self) -> bool {
         let last = self.prev_mood.clone();
-        let new_mood = HumanoidControl::mood::<Worker>(&self);
+        let new_mood = HumanoidControl::mood::<Worker>(self);
         let result = last != new_mood;
-        self.prev_mood = HumanoidControl::mood::<Worker>(&self);
-        return result
-    } 
+        self.prev_mood = HumanoidControl::mood::<Worker>(self);
+        result
+    }
 }
 
 impl Humanoid for Shouter {
@@ -261,6 +369,6 @@ impl Humanoid for Shouter {
     }
 
     fn mood_changed(&mut self) -> bool {
-        return false
-    } 
-}
\ No newline at end of file
+        false
+    }
+}
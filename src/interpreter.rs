@@ -1,8 +1,18 @@
 
-use crate::lexer::{Token, LexerError};
-use crate::parser::{Parser, Value, ASTNode, CompareType};
-use crate::humanoid::{Shouter, Worker};
-use std::collections::HashMap;
+use crate::lexer::{Lexer, Token, Keyword, LexerError, SNIPPET_VARIABLE};
+use crate::parser::{Parser, Value, ASTNode, CompareType, LogicType};
+use crate::builtins;
+use crate::console::Console;
+use crate::format::NumberFormat;
+use crate::session::{Session, Transcript};
+use crate::humanoid::{HumanoidControl, HumanoidSettings, Language, Manager, Shouter, Worker};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, IsTerminal, Read, Write};
+use std::path::Path;
 use std::string::String;
 use std::rc::Rc;
 
@@ -10,109 +20,609 @@ pub struct Interpreter {
     parser: Parser,
     call_stack: Vec<Scope>,
     worker: Worker,
-    shouter: Shouter
+    shouter: Shouter,
+    manager: Manager,
+    console: Console,
+    step_limit: Option<usize>,
+    steps: usize,
+    call_depth_limit: Option<usize>,
+    loop_limit: Option<usize>,
+    dump_state: bool,
+    number_format: NumberFormat,
+    session: Session,
+    // Names of the user-defined functions currently executing.
+    // Left untouched when an error unwinds, so it doubles as traceback.
+    function_names: Vec<String>,
+    // How often each function was called by name, for aufrufe().
+    call_counts: HashMap<String, usize>,
+    traceback: bool,
+    block_scoping: bool,
+    humanoid_stats: bool,
+    args: Vec<String>,
+    environment_access: bool,
+    #[cfg(feature = "bignum")]
+    big_integers: bool
 }
 
+// Configures an Interpreter without going through the environment.
+pub struct InterpreterBuilder {
+    humanoids: bool,
+    humanoid_stats: bool,
+    seed: Option<u64>,
+    language: Language,
+    step_limit: Option<usize>,
+    loop_limit: Option<usize>,
+    output_limit: Option<usize>,
+    call_depth_limit: Option<usize>,
+    dump_state: bool,
+    number_format: NumberFormat,
+    session: Session,
+    traceback: bool,
+    block_scoping: bool,
+    delays: Option<bool>,
+    #[cfg(feature = "bignum")]
+    big_integers: bool,
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    error_output: Box<dyn Write>,
+    echo_input: bool,
+    args: Vec<String>,
+    environment_access: bool
+}
+
+impl Default for InterpreterBuilder {
+    fn default() -> Self {
+        InterpreterBuilder {
+            humanoids: false,
+            humanoid_stats: false,
+            seed: None,
+            language: Language::default(),
+            step_limit: None,
+            loop_limit: None,
+            output_limit: None,
+            call_depth_limit: Some(1000),
+            dump_state: false,
+            number_format: NumberFormat::default(),
+            session: Session::live(),
+            traceback: false,
+            block_scoping: false,
+            delays: None,
+            #[cfg(feature = "bignum")]
+            big_integers: false,
+            input: Box::new(BufReader::new(std::io::stdin())),
+            output: Box::new(std::io::stdout()),
+            error_output: Box::new(std::io::stderr()),
+            echo_input: false,
+            args: Vec::new(),
+            environment_access: false
+        }
+    }
+}
+
+impl InterpreterBuilder {
+    pub fn new() -> Self {
+        InterpreterBuilder::default()
+    }
+
+    pub fn humanoids(mut self, enabled: bool) -> Self {
+        self.humanoids = enabled;
+        self
+    }
+
+    // Reports the humanoids' mood at the end, in strict work they track it silently.
+    pub fn humanoid_stats(mut self, enabled: bool) -> Self {
+        self.humanoid_stats = enabled;
+        self
+    }
+
+    // Fixes the humanoids' random decisions.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn language(mut self, language: Language) -> Self {
+        self.language = language;
+        self
+    }
+
+    // Maximum number of AST nodes visited before giving up.
+    pub fn step_limit(mut self, limit: usize) -> Self {
+        self.step_limit = Some(limit);
+        self
+    }
+
+    // Maximum number of iterations of a single schleif/erstma loop.
+    pub fn loop_limit(mut self, limit: usize) -> Self {
+        self.loop_limit = Some(limit);
+        self
+    }
+
+    // Maximum number of nested function calls, so runaway recursion fails with StackOverflow
+    // before the interpreter itself runs out of stack. None lifts it.
+    pub fn call_depth_limit(mut self, limit: Option<usize>) -> Self {
+        self.call_depth_limit = limit;
+        self
+    }
+
+    // Maximum number of bytes the program may print, to output and error output together.
+    pub fn output_limit(mut self, limit: usize) -> Self {
+        self.output_limit = Some(limit);
+        self
+    }
+
+    // Prints the top-level variables as JSON once the program is done.
+    pub fn dump_state(mut self, enabled: bool) -> Self {
+        self.dump_state = enabled;
+        self
+    }
+
+    // How the output functions print numbers.
+    pub fn number_format(mut self, format: NumberFormat) -> Self {
+        self.number_format = format;
+        self
+    }
+
+    // Records the session into a transcript or replays one, see Interpreter::transcript.
+    pub fn session(mut self, session: Session) -> Self {
+        self.session = session;
+        self
+    }
+
+    // Prints the called functions when the program fails.
+    pub fn traceback(mut self, enabled: bool) -> Self {
+        self.traceback = enabled;
+        self
+    }
+
+    // Drops variables first assigned inside if/loop bodies after the body.
+    pub fn block_scoping(mut self, enabled: bool) -> Self {
+        self.block_scoping = enabled;
+        self
+    }
+
+    // Integer arithmetic on arbitrary-precision integers instead of i32.
+    #[cfg(feature = "bignum")]
+    pub fn big_integers(mut self, enabled: bool) -> Self {
+        self.big_integers = enabled;
+        self
+    }
+
+    // The command-line arguments the program gets from args().
+    pub fn args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    // Lets programs read environment variables with umgebung(), off so sandboxed programs can't.
+    pub fn environment_access(mut self, enabled: bool) -> Self {
+        self.environment_access = enabled;
+        self
+    }
+
+    // Lets the humanoids pause for dramatic effect.
+    // By default they only do so when stdout is a terminal, nobody watches a pipe.
+    pub fn delays(mut self, enabled: bool) -> Self {
+        self.delays = Some(enabled);
+        self
+    }
+
+    pub fn input(mut self, input: Box<dyn BufRead>) -> Self {
+        self.input = input;
+        self
+    }
+
+    // Reads all answers line by line from a file of recorded answers instead of stdin.
+    pub fn answers_file<P: AsRef<Path>>(self, path: P) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        Ok(self.input(Box::new(BufReader::new(file))))
+    }
+
+    pub fn output(mut self, output: Box<dyn Write>) -> Self {
+        self.output = output;
+        self
+    }
+
+    // Where :O__err writes to, stderr by default.
+    pub fn error_output(mut self, error_output: Box<dyn Write>) -> Self {
+        self.error_output = error_output;
+        self
+    }
+
+    // Repeats each line read from the input on the output.
+    pub fn echo_input(mut self, enabled: bool) -> Self {
+        self.echo_input = enabled;
+        self
+    }
+
+    // Reads the whole program from the reader, e.g. a pipe into stdin.
+    // With envelope, the program may leave out hallo and reicht dann auch mal.
+    pub fn build_from_reader(self, mut reader: impl Read, envelope: bool) -> std::io::Result<Interpreter> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let lexer = if envelope { Lexer::new_with_envelope(&text) } else { Lexer::new(&text) };
+        Ok(self.build(Parser::new(lexer)))
+    }
+
+    pub fn build(self, parser: Parser) -> Interpreter {
+        let settings = HumanoidSettings {
+            strict_work: !self.humanoids,
+            silent_stats: self.humanoid_stats,
+            language: self.language,
+            delays: self.delays.unwrap_or_else(|| std::io::stdout().is_terminal())
+        };
+        let seed = self.session.replay_seed().or(self.seed).unwrap_or_else(rand::random);
+        self.session.start(seed);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let worker_rng = StdRng::from_rng(&mut rng).expect("RNG error.");
+        let shouter_rng = StdRng::from_rng(&mut rng).expect("RNG error.");
+        Interpreter {
+            parser,
+            call_stack: vec![Scope::new()],
+            worker: Worker::new(settings, worker_rng, self.session.clone()),
+            shouter: Shouter::new(settings, shouter_rng, self.session.clone()),
+            manager: Manager::new(settings),
+            console: Console::new(self.input, self.output, self.error_output, self.echo_input, self.session.clone(), self.output_limit),
+            session: self.session,
+            step_limit: self.step_limit,
+            steps: 0,
+            call_depth_limit: self.call_depth_limit,
+            loop_limit: self.loop_limit,
+            dump_state: self.dump_state,
+            number_format: self.number_format,
+            function_names: Vec::new(),
+            call_counts: HashMap::new(),
+            traceback: self.traceback,
+            block_scoping: self.block_scoping,
+            humanoid_stats: self.humanoid_stats,
+            args: self.args,
+            environment_access: self.environment_access,
+            #[cfg(feature = "bignum")]
+            big_integers: self.big_integers
+        }
+    }
+}
+
+
+// Room a function call needs before its body is visited, more than one level of dmm calls takes.
+const STACK_RED_ZONE: usize = 1024 * 1024;
+// Size of each additional stack segment.
+const STACK_GROWTH: usize = 16 * 1024 * 1024;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Scope {
     pub symbol_table: HashMap<String, Value>,
-    pub function_table: HashMap<String, Rc<ASTNode>>
+    pub function_table: HashMap<String, Rc<ASTNode>>,
+    // Iterations of the running loops, innermost last, as seen by zaehler().
+    pub loop_counters: Vec<usize>
 }
 
 impl Scope {
     pub fn new() ->  Self {
         Scope {
             symbol_table: HashMap::new(),
-            function_table: HashMap::new()
+            function_table: HashMap::new(),
+            loop_counters: Vec::new()
         }
     }
+
+    // A variable of this scope, or else a top-level one, which every function can read.
+    pub fn variable<'a>(&'a self, globals: &'a Scope, name: &str) -> Option<&'a Value> {
+        self.symbol_table.get(name).or_else(|| globals.symbol_table.get(name))
+    }
 }
 
 #[derive(Debug)]
 pub enum InterpreterError {
     HackyReturn(Value),
     DisturbedWorker,
+    StepLimitExceeded(usize),
+    // More nested function calls than allowed by InterpreterBuilder::call_depth_limit.
+    StackOverflow(usize),
+    // More bytes written than allowed by InterpreterBuilder::output_limit.
+    OutputLimitExceeded(usize),
+    LoopLimitExceeded(usize),
+    // Leaves the innermost loop, or the one with the label.
+    BreakLoop(Option<String>),
+    // A raus that found no loop to leave.
+    BreakOutsideLoop(Option<String>),
+    // Skips to the next iteration of the innermost loop, or the one with the label.
+    ContinueLoop(Option<String>),
+    // A nochmal that found no loop to go on with.
+    ContinueOutsideLoop(Option<String>),
+    TypeError(String),
+    UnknownVariable(String),
+    UnknownFunction(String),
+    FunctionRedeclared(String),
+    // A function declared with the name of a built-in one.
+    ReservedName(String),
+    // Something the interpreter wasn't allowed to do, like umgebung() without environment access.
+    NotAllowed(String),
+    ArgumentCountMismatch {
+        function: String,
+        expected: usize,
+        found: usize
+    },
+    IndexOutOfRange {
+        index: i32,
+        length: usize
+    },
+    InvalidArgument(String),
+    IntegerOverflow,
+    // Dividing or taking the remainder by 0 (or 0.0).
+    DivisionByZero,
+    // Raised by the program itself via fehler(<message>).
+    UserError(String),
+    // Text that was supposed to hold a value but doesn't, as in text_zu_liste.
+    ParseError(String),
+    Syntax(LexerError),
 }
 
 impl Interpreter {
 
     pub fn new(parser: Parser, strict_work: bool) -> Self {
-        Interpreter {
-            parser,
-            call_stack: vec![Scope::new()],
-            worker: Worker::new(strict_work),
-            shouter: Shouter::new(strict_work),
-        }
+        InterpreterBuilder::new().humanoids(!strict_work).build(parser)
     }
 
-    fn expect(value: Value) -> i32 {
+    fn expect(value: Value) -> Result<i32, InterpreterError> {
         match value {
             Value::Integer(v) => {
-                v
+                Ok(v)
+            },
+            _ => {
+                Err(InterpreterError::TypeError(format!("Expected an integer, found {:?}", value)))
+            }
+        }
+    }
+
+    fn expect_float(value: &Value) -> Result<f64, InterpreterError> {
+        value.as_float().ok_or_else(|| InterpreterError::TypeError(format!("Expected a number, found {:?}", value)))
+    }
+
+    // Integers stay integers, so 1 / 2 is 0. Once a float is involved, both sides are floats.
+    fn binary(&self, token: &Token, left: Value, right: Value) -> Result<Value, InterpreterError> {
+        match (&left, &right) {
+            (Value::String(left), Value::String(right)) if *token == Token::Plus => {
+                Ok(Value::String(format!("{}{}", left, right)))
             },
+            (Value::String(_), _) | (_, Value::String(_)) if *token == Token::Plus => {
+                Err(InterpreterError::TypeError(format!("Cannot add {:?} and {:?}, only two strings", left, right)))
+            },
+            (Value::String(_), _) | (_, Value::String(_)) => {
+                Err(InterpreterError::TypeError(format!("Strings can only be added, found {:?} and {:?}", left, right)))
+            },
+            (Value::Float(_), _) | (_, Value::Float(_)) => {
+                let left = Interpreter::expect_float(&left)?;
+                let right = Interpreter::expect_float(&right)?;
+                if right == 0.0 && matches!(token, Token::Divide | Token::Modulo) {
+                    return Err(InterpreterError::DivisionByZero);
+                }
+                Ok(Value::Float(match token {
+                    Token::Plus => left + right,
+                    Token::Minus => left - right,
+                    Token::Multiply => left * right,
+                    Token::Divide => left / right,
+                    Token::Modulo => left % right,
+                    _ => {panic!("Invalid BinaryOp Token: {:?}", token);}
+                }))
+            },
+            #[cfg(feature = "bignum")]
+            _ if self.big_integers => crate::bignum::binary(token, &left, &right),
             _ => {
-                panic!("Not a number!");
+                let left = Interpreter::expect(left)?;
+                let right = Interpreter::expect(right)?;
+                let result = match token {
+                    Token::Plus => {left.checked_add(right)},
+                    Token::Minus => {left.checked_sub(right)},
+                    Token::Multiply => {left.checked_mul(right)},
+                    Token::Divide => {
+                        if right == 0 {
+                            return Err(InterpreterError::DivisionByZero);
+                        }
+                        left.checked_div(right)
+                    },
+                    Token::Modulo => {
+                        if right == 0 {
+                            return Err(InterpreterError::DivisionByZero);
+                        }
+                        // Only the quotient of i32::MIN / -1 overflows, the remainder is just 0.
+                        Some(left.wrapping_rem(right))
+                    },
+                    _ => {panic!("Invalid BinaryOp Token: {:?}", token);}
+                };
+                result.map(Value::Integer).ok_or(InterpreterError::IntegerOverflow)
             }
         }
     }
 
+    fn unary(&self, token: &Token, value: Value) -> Result<Value, InterpreterError> {
+        if *token == Token::ReservedKeyword(Keyword::Not) {
+            return match value {
+                Value::Boolean(b) => Ok(Value::Boolean(!b)),
+                _ => Err(InterpreterError::TypeError(format!("nicht expects a boolean, found {:?}", value)))
+            };
+        }
+        match value {
+            Value::Float(float) => Ok(Value::Float(
+                match token {
+                    Token::Plus => float,
+                    Token::Minus => -float,
+                    _ => {panic!("Invalid UnaryOp Token")}
+                }
+            )),
+            #[cfg(feature = "bignum")]
+            _ if self.big_integers => crate::bignum::unary(token, &value),
+            _ => {
+                let value = Interpreter::expect(value)?;
+                let result = match token {
+                    Token::Plus => {Some(value)},
+                    Token::Minus => {value.checked_neg()},
+                    _ => {panic!("Invalid UnaryOp Token")}
+                };
+                result.map(Value::Integer).ok_or(InterpreterError::IntegerOverflow)
+            }
+        }
+    }
+
+    fn compare(compare_type: &CompareType, left: &Value, right: &Value) -> Result<bool, InterpreterError> {
+        match compare_type {
+            CompareType::Equals => Ok(left.equals(right)),
+            CompareType::Less | CompareType::Greater => {
+                let expected = if *compare_type == CompareType::Less { Ordering::Less } else { Ordering::Greater };
+                match left.ordering(right) {
+                    Some(ordering) => Ok(ordering == expected),
+                    None => Err(InterpreterError::TypeError(format!("Cannot order {:?} and {:?}", left, right)))
+                }
+            }
+        }
+    }
+
+    fn expect_boolean(value: Value) -> Result<bool, InterpreterError> {
+        match value {
+            Value::Boolean(b) => Ok(b),
+            _ => Err(InterpreterError::TypeError(format!("und/oda expect booleans, found {:?}", value)))
+        }
+    }
+
     fn scope(&self) -> &Scope {
-        let scope = self.call_stack.last().expect("Empty callstack! :s");
-        scope
+        self.call_stack.last().expect("Empty callstack! :s")
     }
 
     fn scope_mut(&mut self) -> &mut Scope {
-       let scope = self.call_stack.last_mut().expect("Empty callstack! :s");
-       scope
+       self.call_stack.last_mut().expect("Empty callstack! :s")
     }
 
-    fn resolve_variable(&self, name: &String) -> Value {
-        match self.scope().symbol_table.get(name) {
+    // Local variables first, then the top-level ones.
+    // Variables of the calling functions stay invisible.
+    fn lookup_variable(&self, name: &str) -> Option<&Value> {
+        self.scope().variable(self.globals(), name)
+    }
+
+    fn globals(&self) -> &Scope {
+        self.call_stack.first().expect("Empty callstack! :s")
+    }
+
+    fn resolve_variable(&self, name: &str) -> Result<Value, InterpreterError> {
+        match self.lookup_variable(name) {
             Some(value) => {
-                return value.clone()
+                Ok(value.clone())
             },
             None => {
-                panic!("Unknown variable name: {}", name);
+                Err(InterpreterError::UnknownVariable(name.to_string()))
             }
         }
     }
 
-    fn resolve_function(&self, name: &String) -> Rc<ASTNode> {
+    fn resolve_function(&self, name: &String) -> Result<Rc<ASTNode>, InterpreterError> {
         match self.scope().function_table.get(name) {
             Some(value) => {
-                return value.clone()
+                Ok(value.clone())
             },
             None => {
-                panic!("Unknown variable name: {}", name);
+                Err(InterpreterError::UnknownFunction(name.clone()))
             }
         }
     }
 
+    // Evaluates the arguments of a function the interpreter handles itself.
+    fn visit_arguments(&mut self, name: &str, parameters: &[ASTNode], count: usize) -> Result<Vec<Value>, InterpreterError> {
+        if parameters.len() != count {
+            return Err(InterpreterError::ArgumentCountMismatch { function: name.to_string(), expected: count, found: parameters.len() });
+        }
+        let mut arguments = Vec::new();
+        for parameter in parameters {
+            arguments.push(self.visit(parameter)?);
+        }
+        Ok(arguments)
+    }
+
+    // Joins the printed form of the parameters, as done by :O__ and d;D.
+    fn stringify_parameters(&mut self, parameters: &[ASTNode], separator: &str) -> Result<String, InterpreterError> {
+        let mut texts = Vec::new();
+        for parameter in parameters {
+            match parameter {
+                ASTNode::Variable {name, ..} => {
+                    texts.push(self.number_format.format(&self.resolve_variable(name)?));
+                },
+                _ => {
+                    let value = self.visit(parameter)?;
+                    texts.push(self.number_format.format(&value));
+                }
+            }
+        }
+        Ok(texts.join(separator))
+    }
+
+    // Visits the body of an if or loop, which may get its own variables.
+    fn visit_body(&mut self, body: &ASTNode) -> Result<Value, InterpreterError> {
+        if !self.block_scoping {
+            return self.visit(body);
+        }
+        let outer: HashSet<String> = self.scope().symbol_table.keys().cloned().collect();
+        let result = self.visit(body);
+        self.scope_mut().symbol_table.retain(|name, _| outer.contains(name));
+        result
+    }
+
+    // Gives the loop its own counter for zaehler() while it runs.
+    fn counted_loop(&mut self, run: impl FnOnce(&mut Self) -> Result<(), InterpreterError>) -> Result<Value, InterpreterError> {
+        self.scope_mut().loop_counters.push(0);
+        let result = run(self);
+        self.scope_mut().loop_counters.pop();
+        result.map(|_| Value::None)
+    }
+
+    fn next_iteration(&mut self) -> usize {
+        let counter = self.scope_mut().loop_counters.last_mut().expect("Iteration outside of a loop.");
+        *counter += 1;
+        *counter
+    }
+
+    fn count_iteration(&mut self) -> Result<(), InterpreterError> {
+        let iterations = self.next_iteration();
+        match self.loop_limit {
+            Some(limit) if iterations > limit => Err(InterpreterError::LoopLimitExceeded(limit)),
+            _ => Ok(())
+        }
+    }
+
+    // Runs one iteration, false when the loop was left with raus.
+    // A raus or nochmal naming another loop keeps travelling outwards.
+    fn visit_loop_body(&mut self, body: &ASTNode, label: &Option<String>) -> Result<bool, InterpreterError> {
+        match self.visit_body(body) {
+            Ok(_) => Ok(true),
+            Err(InterpreterError::BreakLoop(target)) if target.is_none() || target == *label => Ok(false),
+            Err(InterpreterError::ContinueLoop(target)) if target.is_none() || target == *label => Ok(true),
+            Err(e) => Err(e)
+        }
+    }
+
     fn visit(&mut self, node: &ASTNode) -> Result<Value, InterpreterError> {
+        self.steps += 1;
+        if let Some(limit) = self.step_limit {
+            if self.steps > limit {
+                return Err(InterpreterError::StepLimitExceeded(limit));
+            }
+        }
         let result = match node {
                 ASTNode::BinOp {left, right, token} => {
-                    Value::Integer(
-                        match token {
-                            Token::Plus => {Interpreter::expect(self.visit(left)?) + Interpreter::expect(self.visit(right)?)},
-                            Token::Minus => {Interpreter::expect(self.visit(left)?) - Interpreter::expect(self.visit(right)?)},
-                            Token::Multiply => {Interpreter::expect(self.visit(left)?) * Interpreter::expect(self.visit(right)?)},
-                            Token::Divide => {Interpreter::expect(self.visit(left)?) / Interpreter::expect(self.visit(right)?)},
-                            _ => {panic!("Invalid BinaryOp Token: {:?}", token);}
-                        }
-                     )
+                    let left = self.visit(left)?;
+                    let right = self.visit(right)?;
+                    self.binary(token, left, right)?
                 },
                 ASTNode::Value {value} => {
                     value.clone()
                 },
+                ASTNode::List {elements} => {
+                    let mut values = Vec::new();
+                    for element in elements {
+                        values.push(self.visit(element)?);
+                    }
+                    Value::List(values)
+                },
                 ASTNode::UnaryOp {expression, token} => {
-                    Value::Integer(
-                        match token {
-                            Token::Plus => {Interpreter::expect(self.visit(expression)?)},
-                            Token::Minus => {-Interpreter::expect(self.visit(expression)?)},
-                            _ => {panic!("Invalid UnaryOp Token")},
-                        }
-                    )
+                    let value = self.visit(expression)?;
+                    self.unary(token, value)?
                 },
                 ASTNode::Block {children} => {
                     for child in children {
@@ -130,11 +640,11 @@ impl Interpreter {
                     Value::None
                 },
                 ASTNode::Variable {name, ..} => {
-                    self.resolve_variable(name)
+                    self.resolve_variable(name)?
                 },
                 ASTNode::Assign {left, right} => {
                     match &**left {
-                        ASTNode::Variable{name} => {
+                        ASTNode::Variable{name, ..} => {
                             let value = self.visit(right)?;
                             self.scope_mut().symbol_table.insert(name.clone(), value);
                         }
@@ -142,14 +652,16 @@ impl Interpreter {
                     }
                     Value::None
                 },
-                ASTNode::If {condition, execution} => {
+                ASTNode::If {condition, execution, else_execution} => {
                     let result = self.visit(condition)?;
                     match result {
                         Value::Boolean(true) => {
-                            self.visit(execution)?;
+                            self.visit_body(execution)?;
                         },
                         Value::Boolean(false) => {
- 
+                            if let Some(else_execution) = else_execution {
+                                self.visit_body(else_execution)?;
+                            }
                         },
                         _ => {
                             return Err(InterpreterError::DisturbedWorker);
@@ -158,128 +670,448 @@ impl Interpreter {
 
                     Value::None
                 },
-                ASTNode::Loop {condition, execution} => {
-                    while let Value::Boolean(true) = self.visit(condition)? {
-                        self.visit(execution)?;
+                ASTNode::Match {subject, arms, default} => {
+                    let subject = self.visit(subject)?;
+                    let mut execution = default.as_deref();
+                    for (value, arm) in arms {
+                        if self.visit(value)?.equals(&subject) {
+                            execution = Some(arm);
+                            break;
+                        }
+                    }
+                    if let Some(execution) = execution {
+                        self.visit_body(execution)?;
                     }
                     Value::None
                 },
+                ASTNode::Loop {condition, execution, label} => {
+                    self.counted_loop(|this| {
+                        while let Value::Boolean(true) = this.visit(condition)? {
+                            this.count_iteration()?;
+                            if !this.visit_loop_body(execution, label)? {
+                                break;
+                            }
+                        }
+                        Ok(())
+                    })?
+                },
+                ASTNode::Repeat {count, execution, label} => {
+                    let count = match self.visit(count)? {
+                        Value::Integer(count) if count >= 0 => count,
+                        Value::Integer(count) => {
+                            return Err(InterpreterError::InvalidArgument(format!("Cannot repeat {} times", count)));
+                        },
+                        value => {
+                            return Err(InterpreterError::TypeError(format!("wiederhole expects an integer, found {:?}", value)));
+                        }
+                    };
+                    self.counted_loop(|this| {
+                        for _ in 0..count {
+                            this.next_iteration();
+                            if !this.visit_loop_body(execution, label)? {
+                                break;
+                            }
+                        }
+                        Ok(())
+                    })?
+                },
+                ASTNode::DoLoop {execution, condition, label} => {
+                    self.counted_loop(|this| {
+                        this.count_iteration()?;
+                        if this.visit_loop_body(execution, label)? {
+                            while let Value::Boolean(true) = this.visit(condition)? {
+                                this.count_iteration()?;
+                                if !this.visit_loop_body(execution, label)? {
+                                    break;
+                                }
+                            }
+                        }
+                        Ok(())
+                    })?
+                },
+                ASTNode::Break {label} => {
+                    return Err(InterpreterError::BreakLoop(label.clone()));
+                },
+                ASTNode::Continue {label} => {
+                    return Err(InterpreterError::ContinueLoop(label.clone()));
+                },
                 ASTNode::Compare {compare_type, left, right} => {
                     let left_result = self.visit(left)?;
                     let right_result = self.visit(right)?;
-                    match compare_type {
-                        CompareType::Equals => {
-                            return Ok(Value::Boolean(left_result == right_result));
-                        },
-                        CompareType::Less => {
-                            return Ok(Value::Boolean(left_result < right_result));
+                    Value::Boolean(Interpreter::compare(compare_type, &left_result, &right_result)?)
+                },
+                ASTNode::CompareChain {operands, compare_types} => {
+                    // Stops at the first comparison that fails, later operands aren't evaluated.
+                    let mut left_result = self.visit(&operands[0])?;
+                    let mut holds = true;
+                    for (compare_type, operand) in compare_types.iter().zip(&operands[1..]) {
+                        let right_result = self.visit(operand)?;
+                        if !Interpreter::compare(compare_type, &left_result, &right_result)? {
+                            holds = false;
+                            break;
+                        }
+                        left_result = right_result;
+                    }
+                    Value::Boolean(holds)
+                },
+                ASTNode::LogicOp {left, right, op} => {
+                    // The right side is only evaluated when it decides the result.
+                    let left_result = Interpreter::expect_boolean(self.visit(left)?)?;
+                    match (op, left_result) {
+                        (LogicType::And, false) => Value::Boolean(false),
+                        (LogicType::Or, true) => Value::Boolean(true),
+                        _ => Value::Boolean(Interpreter::expect_boolean(self.visit(right)?)?)
+                    }
+                },
+                ASTNode::Index {target, index} => {
+                    let target = self.visit(target)?;
+                    let index = match self.visit(index)? {
+                        Value::Integer(index) => index,
+                        value => {
+                            return Err(InterpreterError::TypeError(format!("Index must be an integer, found {:?}", value)));
+                        }
+                    };
+                    // Unlike hol(), [] doesn't count from the end.
+                    let element = |length: usize| usize::try_from(index).ok()
+                        .filter(|position| *position < length)
+                        .ok_or(InterpreterError::IndexOutOfRange {index, length});
+                    match target {
+                        Value::List(values) => values[element(values.len())?].clone(),
+                        Value::String(string) => {
+                            let position = element(string.chars().count())?;
+                            Value::String(string.chars().nth(position).expect("Checked to be in range.").to_string())
                         },
-                        CompareType::Greater => {
-                            return Ok(Value::Boolean(left_result > right_result));
+                        value => {
+                            return Err(InterpreterError::TypeError(format!("Only lists and strings can be indexed, found {:?}", value)));
                         }
                     }
                 },
-                ASTNode::FunctionDeclaration {name, parameters: _, execution_block: _} => {
-                    if None != self.scope_mut().function_table.insert(name.clone(), Rc::new(node.clone())) {
-                        panic!("Function {:?} redeclared!", name);
+                ASTNode::FunctionDeclaration {name, ..} => {
+                    if self.scope().function_table.contains_key(name) {
+                        return Err(InterpreterError::FunctionRedeclared(name.clone()));
+                    }
+                    if builtins::is_builtin(name) {
+                        return Err(InterpreterError::ReservedName(name.clone()));
                     }
+                    self.scope_mut().function_table.insert(name.clone(), Rc::new(node.clone()));
                     Value::None
                 },
                 ASTNode::FunctionCall {function, parameters} => {
-                    match &**function {
-                        ASTNode::Variable{name} => {
-                            // Hard-coded Output Function
-                            if name.starts_with(":O__") {
-                                let mut text = String::new(); 
-                                for parameter in parameters {
-                                    match parameter {
-                                        ASTNode::Variable {name, ..} => {
-                                            text.push_str(format!("{}", self.resolve_variable(name).to_string()).as_str());
-                                        },
-                                        _ =>{text.push_str(format!("{}", self.visit(parameter)?).as_str());}
-                                    }
+                    if let ASTNode::Variable{name, ..} = &**function {
+                        // Hard-coded Output Function
+                        if name == ":O__err" {
+                            // Diagnostics, not shouted, nobody wants to decipher those.
+                            let text = self.stringify_parameters(parameters, "")?;
+                            self.console.write_error(&text);
+                            Value::None
+                        } else if name.starts_with(":O__") && name.ends_with("sep") {
+                            // :O__sep(separator, ...) puts the separator between the rest.
+                            let (separator, rest) = match parameters.split_first() {
+                                Some((separator, rest)) => (self.visit(separator)?.to_string(), rest),
+                                None => {
+                                    return Err(InterpreterError::ArgumentCountMismatch { function: name.clone(), expected: 1, found: 0 });
                                 }
-                                self.shouter.shout(name.len() - 3, text);
-                            } else if name == "d;D" {
-                                let mut text = String::new(); 
-                                for parameter in parameters {
-                                    match parameter {
-                                        ASTNode::Variable {name, ..} => {
-                                            text.push_str(format!("{}", self.resolve_variable(name).to_string()).as_str());
-                                        },
-                                        _ =>{text.push_str(format!("{}", self.visit(parameter)?).as_str());}
-                                    }
+                            };
+                            let text = self.stringify_parameters(rest, &separator)?;
+                            self.shouter.shout(&mut self.console, name.len() - 6, text);
+                            Value::None
+                        } else if name.starts_with(":O__") {
+                            let text = self.stringify_parameters(parameters, "")?;
+                            self.shouter.shout(&mut self.console, name.len() - 3, text);
+                            Value::None
+                        } else if name == "d;D" {
+                            let mut text = self.stringify_parameters(parameters, "")?;
+                            text.push_str(": ");
+                            self.console.read_value(&text)
+                        } else if name == "args" {
+                            if !parameters.is_empty() {
+                                return Err(InterpreterError::ArgumentCountMismatch { function: name.clone(), expected: 0, found: parameters.len() });
+                            }
+                            Value::List(self.args.iter().map(|arg| Value::String(arg.clone())).collect())
+                        } else if name == "zaehler" {
+                            // The current iteration of the innermost loop, starting at 1.
+                            if !parameters.is_empty() {
+                                return Err(InterpreterError::ArgumentCountMismatch { function: name.clone(), expected: 0, found: parameters.len() });
+                            }
+                            match self.scope().loop_counters.last() {
+                                Some(&iterations) => Value::Integer(i32::try_from(iterations).map_err(|_| InterpreterError::IntegerOverflow)?),
+                                None => {
+                                    return Err(InterpreterError::InvalidArgument("zaehler() only works inside a loop".to_string()));
                                 }
-                                text.push_str(": ");
-                                return Ok(crate::humanoid::read_value(&text))
-                            } else {
-                                // User-defined Functions
-
-                                let mut new_scope = Scope::new();
-                                for (k,v) in &self.scope().function_table {
-                                    new_scope.function_table.insert(k.to_string(), v.clone());
+                            }
+                        } else if name == "tiefe" {
+                            // How many function calls deep we are, 0 outside of functions.
+                            if !parameters.is_empty() {
+                                return Err(InterpreterError::ArgumentCountMismatch { function: name.clone(), expected: 0, found: parameters.len() });
+                            }
+                            Value::Integer(i32::try_from(self.call_stack.len() - 1).map_err(|_| InterpreterError::IntegerOverflow)?)
+                        } else if name == "frage_weiter" {
+                            // Only a no stops, without any answer left (piped input at its end) it goes on.
+                            if !parameters.is_empty() {
+                                return Err(InterpreterError::ArgumentCountMismatch { function: name.clone(), expected: 0, found: parameters.len() });
+                            }
+                            let answer = self.console.read_line("Weiter? (ja/nein) ").unwrap_or_default();
+                            Value::Boolean(!matches!(answer.trim().to_lowercase().as_str(), "nein" | "n" | "no" | ":("))
+                        } else if name == "umgebung" {
+                            // The environment variable, or - when it isn't set.
+                            let arguments = self.visit_arguments(name, parameters, 1)?;
+                            if !self.environment_access {
+                                return Err(InterpreterError::NotAllowed("umgebung() needs environment access".to_string()));
+                            }
+                            match &arguments[0] {
+                                Value::String(variable) => std::env::var(variable).map(Value::String).unwrap_or(Value::None),
+                                value => {
+                                    return Err(InterpreterError::TypeError(format!("umgebung expects a string, found {:?}", value)));
                                 }
-                                
-                                if let ASTNode::FunctionDeclaration {name: _, parameters: func_parameters, execution_block} = self.resolve_function(name).as_ref() {
-                                    if func_parameters.len() != parameters.len() {
-                                        panic!("Invalid argument count!");
-                                    }
-                                    // TODO: There is 100% a Rust Solution for enumerating with an index.
-                                    let mut i = 0;
-                                    for parameter in parameters {
-                                        let value = self.visit(parameter)?;
-                                        new_scope.symbol_table.insert(func_parameters.get(i).expect("Function argument missing").clone(), value);
-                                        i = i + 1;
-                                    }
-                                    // Push upon callstack new function scope+
-                                    self.call_stack.push(new_scope);
-    
-                                    let result = match self.visit(&execution_block) {
-                                        Ok(value) => {
-                                            value
-                                        },
-                                        Err(InterpreterError::HackyReturn(value)) => {
-                                            value
-                                        },
-                                        Err(e) => {return Err(e);}
-                                    };
-                                    self.call_stack.pop();
-                                    return Ok(result);
-                                } else {
-                                    panic!("Invalid function stored.");
+                            }
+                        } else if name == "aufrufe" {
+                            // How often the function was called so far, 0 for names never called.
+                            let arguments = self.visit_arguments(name, parameters, 1)?;
+                            match &arguments[0] {
+                                Value::String(function) => {
+                                    let count = self.call_counts.get(function).copied().unwrap_or(0);
+                                    Value::Integer(i32::try_from(count).map_err(|_| InterpreterError::IntegerOverflow)?)
+                                },
+                                value => {
+                                    return Err(InterpreterError::TypeError(format!("aufrufe expects a string, found {:?}", value)));
                                 }
                             }
-                        },
-                        _ => {}
+                        } else if name == "fakultaet" {
+                            let arguments = self.visit_arguments(name, parameters, 1)?;
+                            let n = Interpreter::expect(arguments[0].clone())?;
+                            if n < 0 {
+                                return Err(InterpreterError::InvalidArgument(format!("fakultaet: {} is negative", n)));
+                            }
+                            let mut result = Value::Integer(1);
+                            for i in 2..=n {
+                                result = self.binary(&Token::Multiply, result, Value::Integer(i))?;
+                            }
+                            result
+                        } else if name == "potenz" {
+                            // Square and multiply, so potenz(1, 2000000000) is quick too.
+                            let arguments = self.visit_arguments(name, parameters, 2)?;
+                            let mut base = arguments[0].clone();
+                            Interpreter::expect_float(&base)?;
+                            let mut exponent = Interpreter::expect(arguments[1].clone())?;
+                            if exponent < 0 {
+                                return Err(InterpreterError::InvalidArgument(format!("potenz: exponent {} is negative", exponent)));
+                            }
+                            let mut result = Value::Integer(1);
+                            while exponent > 0 {
+                                if exponent % 2 == 1 {
+                                    result = self.binary(&Token::Multiply, result, base.clone())?;
+                                }
+                                exponent /= 2;
+                                if exponent > 0 {
+                                    base = self.binary(&Token::Multiply, base.clone(), base)?;
+                                }
+                            }
+                            result
+                        } else if name == "zeig_umgebung" {
+                            // Same dump the worker shows when asking, sorted to be readable.
+                            if !parameters.is_empty() {
+                                return Err(InterpreterError::ArgumentCountMismatch { function: name.clone(), expected: 0, found: parameters.len() });
+                            }
+                            let symbols: BTreeMap<&String, &Value> = self.scope().symbol_table.iter().collect();
+                            let mut functions: Vec<&String> = self.scope().function_table.keys().collect();
+                            functions.sort();
+                            let text = format!("Symbols: {:?}\nFunctions: {:?}", symbols, functions);
+                            writeln!(self.console, "{}", text).expect("IO error.");
+                            Value::None
+                        } else if let Some(builtin) = builtins::lookup(name) {
+                            let mut arguments = Vec::new();
+                            for parameter in parameters {
+                                arguments.push(self.visit(parameter)?);
+                            }
+                            builtin(&arguments)?
+                        } else if !self.scope().function_table.contains_key(name) && self.lookup_variable(name).is_some() {
+                            // A variable holding a function written with funny(...).
+                            match self.resolve_variable(name)? {
+                                Value::Function(lambda) => {
+                                    *self.call_counts.entry(name.clone()).or_insert(0) += 1;
+                                    self.call_lambda(&lambda, parameters)?
+                                },
+                                value => {
+                                    return Err(InterpreterError::TypeError(format!("Cannot call {}, it is {:?}", name, value)));
+                                }
+                            }
+                        } else {
+                            // User-defined Functions
+                            if let ASTNode::FunctionDeclaration {parameters: func_parameters, execution_block, ..} = self.resolve_function(name)?.as_ref() {
+                                *self.call_counts.entry(name.clone()).or_insert(0) += 1;
+                                self.call_function(name, func_parameters, execution_block, parameters)?
+                            } else {
+                                panic!("Invalid function stored.");
+                            }
+                        }
+                    } else {
+                        match self.visit(function)? {
+                            Value::Function(lambda) => self.call_lambda(&lambda, parameters)?,
+                            value => {
+                                return Err(InterpreterError::TypeError(format!("Cannot call {:?}", value)));
+                            }
+                        }
                     }
-                    Value::None
+                },
+                ASTNode::Lambda {..} => {
+                    Value::Function(Rc::new(node.clone()))
+                },
+                ASTNode::Commented {statement, ..} => {
+                    return self.visit(statement);
                 },
                 ASTNode::Return{expression} => {
                     // So f...... cursed.
+                    // Skips the worker: the returned expression was already shown to the worker.
                     return Err(InterpreterError::HackyReturn(self.visit(expression)?))
                 },
                 ASTNode::NoOp => {Value::None},
             };
-        self.worker.call(self.call_stack.last().unwrap(), node, &result)?;
+        self.console.check_output_limit()?;
+        self.worker.call(&mut self.console, self.call_stack.last().unwrap(), self.call_stack.first().unwrap(), node, &result)?;
+        Ok(result)
+    }
+
+    // Runs a function body in a new scope, which only sees the functions and the parameters.
+    fn call_function(&mut self, name: &str, func_parameters: &[String], execution_block: &ASTNode, parameters: &[ASTNode]) -> Result<Value, InterpreterError> {
+        let mut new_scope = Scope::new();
+        for (k,v) in &self.scope().function_table {
+            new_scope.function_table.insert(k.to_string(), v.clone());
+        }
+
+        if func_parameters.len() != parameters.len() {
+            return Err(InterpreterError::ArgumentCountMismatch { function: name.to_string(), expected: func_parameters.len(), found: parameters.len() });
+        }
+        if let Some(limit) = self.call_depth_limit {
+            // The first scope belongs to the program, not to a call.
+            if self.call_stack.len() > limit {
+                return Err(InterpreterError::StackOverflow(limit));
+            }
+        }
+        for (i, parameter) in parameters.iter().enumerate() {
+            let value = self.visit(parameter)?;
+            new_scope.symbol_table.insert(func_parameters.get(i).expect("Function argument missing").clone(), value);
+        }
+        // Push upon callstack new function scope+
+        self.call_stack.push(new_scope);
+        self.function_names.push(name.to_string());
+
+        // Deep dmm recursion is deep recursion here too. The stack grows on the heap when it runs low,
+        // so call_depth_limit decides when to stop, not the size of the embedder's thread stack.
+        let result = match stacker::maybe_grow(STACK_RED_ZONE, STACK_GROWTH, || self.visit(execution_block)) {
+            Ok(value) => {
+                value
+            },
+            Err(InterpreterError::HackyReturn(value)) => {
+                value
+            },
+            // Loops of the caller can't be left from inside a function.
+            Err(InterpreterError::BreakLoop(label)) => {
+                return Err(InterpreterError::BreakOutsideLoop(label));
+            },
+            Err(InterpreterError::ContinueLoop(label)) => {
+                return Err(InterpreterError::ContinueOutsideLoop(label));
+            },
+            Err(e) => {return Err(e);}
+        };
+        self.call_stack.pop();
+        self.function_names.pop();
         Ok(result)
     }
 
-    pub fn interpret(&mut self) -> Result<(), LexerError> {
-        let tree = self.parser.parse()?;
-        let result = self.visit(&tree);
-        match result {
-            Ok(_) => {
+    fn call_lambda(&mut self, lambda: &ASTNode, parameters: &[ASTNode]) -> Result<Value, InterpreterError> {
+        match lambda {
+            ASTNode::Lambda {parameters: func_parameters, execution_block} => {
+                self.call_function("funny", func_parameters, execution_block, parameters)
+            },
+            _ => panic!("Invalid function stored.")
+        }
+    }
 
+    // Without a wirf, the value of the last statement is what the program throws at us.
+    // Only on the top level, function bodies still need a wirf.
+    fn visit_program(&mut self, tree: &ASTNode) -> Result<Value, InterpreterError> {
+        match tree {
+            ASTNode::Block {children} => {
+                let mut last = Value::None;
+                for child in children {
+                    last = self.visit(child)?;
+                }
+                Ok(last)
             },
-            Err(InterpreterError::HackyReturn(val)) => {
-                println!("This program throwed at us a: {}", val);
+            _ => self.visit(tree)
+        }
+    }
+
+    // Runs the program and returns the value it throws at us with wirf,
+    // or else the value of its last statement.
+    pub fn run(&mut self) -> Result<Value, InterpreterError> {
+        let tree = self.parser.parse().map_err(InterpreterError::Syntax)?;
+        self.manager.review(&mut self.console, &tree);
+        let result = match self.visit_program(&tree) {
+            Ok(value) => Ok(value),
+            Err(InterpreterError::HackyReturn(value)) => Ok(value),
+            Err(InterpreterError::BreakLoop(label)) => Err(InterpreterError::BreakOutsideLoop(label)),
+            Err(InterpreterError::ContinueLoop(label)) => Err(InterpreterError::ContinueOutsideLoop(label)),
+            Err(e) => Err(e)
+        };
+        self.console.flush().expect("IO error.");
+        result
+    }
+
+    // Runs the program, reporting runtime errors to the output.
+    // Syntax errors are only returned.
+    pub fn interpret(&mut self) -> Result<(), InterpreterError> {
+        let result = self.run();
+        self.console.lift_output_limit();
+        match &result {
+            Ok(Value::None) | Err(InterpreterError::Syntax(_)) => {
+
+            },
+            Ok(val) => {
+                writeln!(self.console, "This program throwed at us a: {}", val).expect("IO error.");
             },
             Err(e) => {
-                println!("Oh oh... {:?}", e);
+                let message = self.worker.describe_error(e);
+                writeln!(self.console, "{}", message).expect("IO error.");
+                if self.traceback {
+                    writeln!(self.console, "Traceback (most recent call last):").expect("IO error.");
+                    for name in &self.function_names {
+                        writeln!(self.console, "    {}", name).expect("IO error.");
+                    }
+                }
             }
         }
-        //dbg!(&tree);
-        //dbg!(&self.symbol_table);
-        Ok(())
+        if self.humanoid_stats {
+            writeln!(self.console, "[ {} stress: {} | {} voice damage: {} ]",
+                HumanoidControl::mood(&self.worker), self.worker.stress_level(),
+                HumanoidControl::mood(&self.shouter), self.shouter.voice_damage()).expect("IO error.");
+        }
+        if self.dump_state {
+            let state = self.state_json();
+            writeln!(self.console, "{}", state).expect("IO error.");
+        }
+        self.console.flush().expect("IO error.");
+        result.map(|_| ())
     }
+
+    // What happened so far in a recording session, to be replayed later.
+    pub fn transcript(&self) -> Transcript {
+        self.session.transcript()
+    }
+
+    // The top-level variables as JSON object, sorted by name.
+    pub fn state_json(&self) -> String {
+        let globals = &self.call_stack.first().expect("Empty callstack! :s").symbol_table;
+        let sorted: BTreeMap<&String, &Value> = globals.iter().collect();
+        serde_json::to_string(&sorted).expect("Values are always serializable.")
+    }
+}
+
+// Evaluates a bare expression without the hallo/reicht dann auch mal envelope.
+pub fn run_snippet(expression: &str) -> Result<Value, InterpreterError> {
+    let parser = Parser::new(Lexer::new_fill_greeting_farewell(expression));
+    let mut interpreter = Interpreter::new(parser, true);
+    let tree = interpreter.parser.parse().map_err(InterpreterError::Syntax)?;
+    interpreter.visit(&tree)?;
+    interpreter.resolve_variable(SNIPPET_VARIABLE)
 }
\ No newline at end of file
@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::Display;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -10,12 +11,24 @@ pub enum Keyword {
     Function,
     Return,
     Loop,
+    DoLoop,
+    DoLoopCondition,
+    Repeat,
+    Label,
+    Break,
+    Continue,
     Equals,
     Less,
     Greater,
+    And,
+    Or,
+    Not,
     AssignPrefix,
     AssignInfix,
-    If
+    If,
+    Match,
+    Case,
+    Default
 }
 
 
@@ -24,17 +37,24 @@ pub enum Token {
     ReservedKeyword(Keyword),
     ID{string: String},
     Integer(u32),
+    Float(f64),
     String(String),
     Boolean(bool),
     Comma,
     Plus,
     Minus,
     Multiply,
+    Modulo,
     Divide,
     ParentheseOpen,
     ParentheseClose,
+    IndexOpen,
+    IndexClose,
+    ListClose,
     EndLine,
     Assign,
+    // Only produced when the lexer keeps comments, see Lexer::keep_comments.
+    Comment(String),
     EOF
 }
 
@@ -44,10 +64,42 @@ impl Display for Token {
     }
 }
 
+// Holds the value of a snippet, see new_fill_greeting_farewell.
+// No variable name the lexer reads contains a $, so programs can neither read nor overwrite it.
+pub const SNIPPET_VARIABLE: &str = "$snippet";
+
 pub struct Lexer {
-    text: String,
+    // Characters rather than a String, so looking at any position is O(1).
+    text: Vec<char>,
     position: usize,
-    reserved_keywords: HashMap<String, Token>
+    token_start: usize,
+    reserved_keywords: HashMap<String, Token>,
+    keep_comments: bool,
+    // Tokens handed out before lexing on, the snippet's start or ones the parser gave back.
+    prelude: VecDeque<Token>
+}
+
+// Line and column in the source, both starting at 1.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize
+}
+
+impl Position {
+    // The position of the character at offset (counted in characters) in text.
+    pub fn from_offset(text: &str, offset: usize) -> Self {
+        let mut position = Position { line: 1, column: 1 };
+        for c in text.chars().take(offset) {
+            if c == '\n' {
+                position.line += 1;
+                position.column = 1;
+            } else {
+                position.column += 1;
+            }
+        }
+        position
+    }
 }
 
 #[derive(Debug)]
@@ -70,12 +122,24 @@ impl Lexer {
             ("colon".to_string(), Token::ReservedKeyword(Keyword::Cado)),
             ("funny".to_string(), Token::ReservedKeyword(Keyword::Function)),
             ("wenn".to_string(), Token::ReservedKeyword(Keyword::If)),
+            ("pruef".to_string(), Token::ReservedKeyword(Keyword::Match)),
+            ("fall".to_string(), Token::ReservedKeyword(Keyword::Case)),
+            ("sonst".to_string(), Token::ReservedKeyword(Keyword::Default)),
             ("wirf".to_string(), Token::ReservedKeyword(Keyword::Return)),
             ("schleif".to_string(), Token::ReservedKeyword(Keyword::Loop)),
             ("immawida".to_string(), Token::ReservedKeyword(Keyword::Loop)),
+            ("erstma".to_string(), Token::ReservedKeyword(Keyword::DoLoop)),
+            ("solange".to_string(), Token::ReservedKeyword(Keyword::DoLoopCondition)),
+            ("wiederhole".to_string(), Token::ReservedKeyword(Keyword::Repeat)),
+            ("namens".to_string(), Token::ReservedKeyword(Keyword::Label)),
+            ("raus".to_string(), Token::ReservedKeyword(Keyword::Break)),
+            ("nochmal".to_string(), Token::ReservedKeyword(Keyword::Continue)),
             ("is".to_string(), Token::ReservedKeyword(Keyword::Equals)),
             ("kleina".to_string(), Token::ReservedKeyword(Keyword::Less)),
             ("krasser".to_string(), Token::ReservedKeyword(Keyword::Greater)),
+            ("und".to_string(), Token::ReservedKeyword(Keyword::And)),
+            ("oda".to_string(), Token::ReservedKeyword(Keyword::Or)),
+            ("nicht".to_string(), Token::ReservedKeyword(Keyword::Not)),
             ("machma".to_string(), Token::ReservedKeyword(Keyword::AssignPrefix)),
             ("uf".to_string(), Token::ReservedKeyword(Keyword::AssignInfix)),
             ].iter().cloned().collect()
@@ -83,33 +147,80 @@ impl Lexer {
 
     pub fn new(text: &str) -> Self {
         Lexer {
-            text: text.to_string(), 
+            text: text.chars().collect(),
             position: 0,
-            reserved_keywords: Lexer::create_keywords()
+            token_start: 0,
+            reserved_keywords: Lexer::create_keywords(),
+            keep_comments: false,
+            prelude: VecDeque::new()
         }
     }
 
-    pub fn new_fill_greeting_farewell(text: &str) -> Self {
-        let mut adapted_text = String::from("hallo\n x = ");
-        adapted_text.push_str(text);
-        adapted_text.push_str("\nreicht dann auch mal");
-        Lexer {
-            text: adapted_text, 
-            position: 0,
-            reserved_keywords: Lexer::create_keywords()
+    // Hands comments out as Token::Comment instead of skipping them, for tooling.
+    pub fn keep_comments(mut self) -> Self {
+        self.keep_comments = true;
+        self
+    }
+
+    fn invalid_character(&self, c: char) -> LexerError {
+        let position = self.source_position();
+        LexerError::InvalidSyntax(format!("No token for '{}' at line {}, col {}", c, position.line, position.column))
+    }
+
+    // Hands the tokens out again, in order, before lexing on.
+    pub fn give_back(&mut self, tokens: Vec<Token>) {
+        for token in tokens.into_iter().rev() {
+            self.prelude.push_front(token);
         }
     }
 
+    // The character offset where the last token (or failed attempt at one) started.
+    pub fn token_start(&self) -> usize {
+        self.token_start
+    }
+
+    // Wraps bare statements into a program with greeting and farewell.
+    // Unlike new_fill_greeting_farewell, nothing gets assigned, so whole scripts fit.
+    pub fn new_with_envelope(text: &str) -> Self {
+        Lexer::new(&format!("hallo\n{}\nreicht dann auch mal", text.trim_end()))
+    }
+
+    // Wraps a bare expression into a program assigning it to SNIPPET_VARIABLE.
+    // The greeting and the assignment come as tokens, SNIPPET_VARIABLE can't be lexed from text.
+    pub fn new_fill_greeting_farewell(text: &str) -> Self {
+        let mut lexer = Lexer::new(&format!("{}\nreicht dann auch mal", text));
+        lexer.prelude = VecDeque::from([
+            Token::ReservedKeyword(Keyword::Greeting),
+            Token::EndLine,
+            Token::ID { string: SNIPPET_VARIABLE.to_string() },
+            Token::Assign
+        ]);
+        lexer
+    }
+
+    fn source_position(&self) -> Position {
+        Position::from_offset(&self.text.iter().collect::<String>(), self.position)
+    }
+
+    fn char_at(&self, offset: usize) -> Option<char> {
+        self.text.get(offset).copied()
+    }
+
     fn current_char(&self) -> Option<char> {
-        self.text.chars().nth(self.position)
+        self.char_at(self.position)
     }
 
     fn peek(&self) -> Option<char> {
-        self.text.chars().nth(self.position + 1)
+        self.char_at(self.position + 1)
     }
 
     fn goto_next_position(&mut self) {
-        self.position = self.position + 1;
+        self.position += 1;
+    }
+
+    // Identifiers are made of these characters and may not equal a keyword.
+    fn is_identifier_char(c: char) -> bool {
+        c.is_alphanumeric() || c == '_'
     }
 
     fn skip_whitespace(&mut self) {
@@ -122,19 +233,54 @@ impl Lexer {
         }
     }
 
-    fn integer(&mut self) -> u32 {
+    fn number(&mut self) -> Result<Token, LexerError> {
+        // 0b, 0o and 0x prefixes select binary, octal and hexadecimal.
+        let prefix = match (self.current_char(), self.peek()) {
+            (Some('0'), Some('b')) => Some(("binary", 2)),
+            (Some('0'), Some('o')) => Some(("octal", 8)),
+            (Some('0'), Some('x')) => Some(("hexadecimal", 16)),
+            _ => None
+        };
+        if let Some((base_name, radix)) = prefix {
+            self.goto_next_position();
+            let mut digits = String::new();
+            while let Some(next_char) = self.peek() {
+                if next_char.is_alphanumeric() {
+                    digits.push(next_char);
+                    self.goto_next_position();
+                } else {
+                    break;
+                }
+            }
+            return u32::from_str_radix(&digits, radix).map(Token::Integer).map_err(|_| LexerError::InvalidSyntax(
+                format!("Invalid {} number: {}", base_name, digits)
+            ));
+        }
+
         let mut number = String::new();
         number.push(self.current_char().unwrap());
+        self.push_digits(&mut number);
 
+        // A point only makes a float when digits follow, 3. stays the integer 3 and a point.
+        let fraction_follows = self.char_at(self.position + 2).is_some_and(|c| c.is_ascii_digit());
+        if self.peek() == Some('.') && fraction_follows {
+            number.push('.');
+            self.goto_next_position();
+            self.push_digits(&mut number);
+            return Ok(Token::Float(number.parse().expect("Digits with one point are a float.")));
+        }
+        number.parse::<u32>().map(Token::Integer).map_err(|_| LexerError::InvalidSyntax(format!("Number too large: {}", number)))
+    }
+
+    fn push_digits(&mut self, number: &mut String) {
         while let Some(next_char) = self.peek() {
-            if next_char.is_digit(10) {
+            if next_char.is_ascii_digit() {
                 number.push(next_char);
                 self.goto_next_position();
             } else {
                 break;
             }
         }
-        number.parse::<u32>().unwrap()
     }
 
     fn keyword_or_string(&mut self) -> Result<Token, LexerError> {
@@ -144,6 +290,24 @@ impl Lexer {
         // String
         if current_char == '<' {
             while let Some(next_char) = self.peek() {
+                if next_char == '\\' {
+                    // \< \> \\ \n and \t, any other backslash stays as it is.
+                    let escaped = match self.char_at(self.position + 2) {
+                        Some(escaped @ ('<' | '>' | '\\')) => Some(escaped),
+                        Some('n') => Some('\n'),
+                        Some('t') => Some('\t'),
+                        Some(_) => None,
+                        None => {
+                            return Err(LexerError::InvalidSyntax("Unterminated escape: \\ at the end of the input".to_string()));
+                        }
+                    };
+                    if let Some(escaped) = escaped {
+                        result.push(escaped);
+                        self.goto_next_position();
+                        self.goto_next_position();
+                        continue;
+                    }
+                }
                 if next_char != '>' {
                     result.push(next_char);
                     self.goto_next_position();
@@ -170,20 +334,23 @@ impl Lexer {
                 result.push(*next_char);
                 self.goto_next_position();
 
-                match self.reserved_keywords.get(&result) {
-                    Some(keyword_token) => {
-                        // self.position = self.position + 1;
-                        return Ok(keyword_token.clone().clone())
+                // Keywords only match whole words, so `isbn` stays a variable.
+                if let Some(keyword_token) = self.reserved_keywords.get(&result) {
+                    if !self.peek().is_some_and(Lexer::is_identifier_char) {
+                        return Ok(keyword_token.clone())
                     }
-                    _ => {}
                 }
             } else {
                 break;
             }
         }
         // Reset to text beginning, if no keyword matched
-        result = result.get(0..1).unwrap().to_string();
+        result = current_char.to_string();
         self.position = start_position;
+        // Variables start like identifiers, or with : for the output functions.
+        if !(Lexer::is_identifier_char(current_char) || current_char == ':') {
+            return Err(self.invalid_character(current_char));
+        }
         // Variable. 
         while let Some(next_char) = &mut self.peek() {
             if next_char.is_alphanumeric() || *next_char == '_' || *next_char == ';' {
@@ -220,31 +387,66 @@ impl Lexer {
         }
     }
 
+    // Reads a comment from # up to the end of the line, stopping before the line break.
+    fn comment(&mut self) -> String {
+        let mut comment = String::new();
+        while let Some(next_char) = self.peek() {
+            if next_char == '\n' {
+                break;
+            }
+            comment.push(next_char);
+            self.goto_next_position();
+        }
+        self.goto_next_position();
+        comment.trim().to_string()
+    }
+
     // Break text into token.
     pub fn get_next_token(&mut self) -> Result<Token, LexerError> {
-        if self.position > self.text.len() - 1 {
+        if let Some(token) = self.prelude.pop_front() {
+            return Ok(token);
+        }
+        // Tokens skip the spaces after them, this covers the ones before the first token.
+        self.skip_whitespace();
+        if self.position >= self.text.len() {
             return Ok(Token::EOF)
         }
 
+        self.token_start = self.position;
+        if self.current_char() == Some('#') {
+            let comment = self.comment();
+            if self.keep_comments {
+                return Ok(Token::Comment(comment));
+            }
+            return self.get_next_token();
+        }
         let mut token : Option<Token> = None;
 
         
 
         if let Some(current_char) = self.current_char() {
-            if current_char.is_digit(10) {
-                token = Some(Token::Integer(self.integer()));
+            if current_char.is_ascii_digit() {
+                token = Some(self.number()?);
             } else if current_char == '+' {
                 token = Some(Token::Plus);
             } else if current_char == '-' {
                 token = Some(Token::Minus);
             } else if current_char == '*' {
                 token = Some(Token::Multiply);
+            } else if current_char == '%' {
+                token = Some(Token::Modulo);
             } else if current_char == '/' {
                 token = Some(Token::Divide);
             } else if current_char == '(' {
                 token = Some(Token::ParentheseOpen);
             } else if current_char == ')' {
                 token = Some(Token::ParentheseClose);
+            } else if current_char == '[' {
+                token = Some(Token::IndexOpen);
+            } else if current_char == ']' {
+                token = Some(Token::IndexClose);
+            } else if current_char == '\\' {
+                token = Some(Token::ListClose);
             } else if current_char == '=' {
                 token = Some(Token::Assign);  
             } else if current_char == '\n' {
@@ -255,7 +457,7 @@ impl Lexer {
                 token = self.smiley();  
             } 
 
-            if token == None {
+            if token.is_none() {
                 token = Some(self.keyword_or_string()?);
             }
         }
@@ -265,7 +467,8 @@ impl Lexer {
             self.skip_whitespace();
             Ok(token)
         } else {
-            Err(LexerError::InvalidSyntax(String::from("No suitable token.")))
+            let position = self.source_position();
+            Err(LexerError::InvalidSyntax(format!("No suitable token at line {}, col {}", position.line, position.column)))
         }
     }
 
@@ -0,0 +1,13 @@
+#[cfg(feature = "bignum")]
+pub mod bignum;
+pub mod builtins;
+pub mod check;
+pub mod console;
+pub mod format;
+pub mod humanoid;
+pub mod interpreter;
+pub mod lexer;
+pub mod parser;
+pub mod repl;
+pub mod session;
+pub mod test_support;
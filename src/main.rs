@@ -1,18 +1,16 @@
-mod humanoid;
-mod interpreter;
-mod lexer;
-mod parser;
-
-use interpreter::Interpreter;
-use lexer::{Lexer, LexerError, Token};
-use parser::Parser;
+use dmm::check::{check_source, DiagnosticKind};
+use dmm::interpreter::{Interpreter, InterpreterBuilder, InterpreterError};
+use dmm::lexer::{Lexer, LexerError, Token};
+use dmm::parser::Parser;
+use dmm::repl::{ReplAction, ReplBuffer};
+use dmm::session::{Session, Transcript};
 use std::env;
 use std::fs;
+use std::fs::File;
 use std::io;
-use std::io::Write;
+use std::io::{IsTerminal, Read, Write};
 
-fn print_tokens(text: String) {
-    let mut lexer = Lexer::new(&text);
+fn print_tokens(mut lexer: Lexer) {
     loop {
         let token_result = lexer.get_next_token();
         match token_result {
@@ -29,64 +27,186 @@ fn print_tokens(text: String) {
         }
     }
 }
-fn print_ast(text: String) {
-    let lexer = Lexer::new(&text);
-    let mut parser = Parser::new(lexer);
+fn print_ast(lexer: Lexer) {
+    let mut parser = Parser::with_comments(lexer);
     let tree = parser.parse().unwrap();
     dbg!(tree);
 }
 
-fn interpret_text(text: String) {
-    let lexer = Lexer::new(&text);
-    let parser = Parser::new(lexer);
-    let mut interpreter = Interpreter::new(parser, std::env::var("USE_HUMANOIDS").is_err());
-
-    match interpreter.interpret() {
-        Ok(()) => {}
-        Err(err) => {
-            println!("{:?}", err)
+fn check_text(text: String, json: bool) -> bool {
+    let diagnostics = check_source(&text);
+    if json {
+        println!("{}", serde_json::to_string(&diagnostics).expect("Diagnostics are always serializable."));
+    } else {
+        for diagnostic in &diagnostics {
+            println!("{}", diagnostic);
         }
+    }
+    diagnostics.iter().all(|diagnostic| diagnostic.kind != DiagnosticKind::Error)
+}
+
+fn interpreter_builder() -> InterpreterBuilder {
+    let builder = InterpreterBuilder::new().humanoids(env::var("USE_HUMANOIDS").is_ok());
+    let builder = match env::var("HUMANOID_ANSWERS") {
+        Ok(path) => builder.answers_file(&path).expect("Cannot read answers file"),
+        Err(_) => builder
     };
+    if let Ok(path) = env::var("HUMANOID_REPLAY") {
+        let text = fs::read_to_string(path).expect("Cannot read transcript");
+        let transcript: Transcript = serde_json::from_str(&text).expect("Invalid transcript");
+        builder.session(Session::replaying(transcript))
+    } else if env::var("HUMANOID_RECORD").is_ok() {
+        builder.session(Session::recording())
+    } else {
+        builder
+    }
+}
+
+// The flags at the start of the arguments.
+fn take_flags(args: &mut Vec<String>) -> Vec<String> {
+    let count = args.iter().take_while(|arg| arg.starts_with("--")).count();
+    args.drain(..count).collect()
+}
+
+// Wrong arguments end dmm before anything runs.
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(2);
+}
+
+fn read_program(path: &str) -> String {
+    fs::read_to_string(path).unwrap_or_else(|err| usage_error(&format!("Cannot read {}: {}", path, err)))
+}
+
+fn interpret_text(text: String, builder: InterpreterBuilder) -> bool {
+    interpret(builder.build(Parser::new(Lexer::new(&text))))
+}
+
+fn interpret(mut interpreter: Interpreter) -> bool {
+    let result = interpreter.interpret();
+    if let Ok(path) = env::var("HUMANOID_RECORD") {
+        let transcript = serde_json::to_string_pretty(&interpreter.transcript()).expect("Transcripts are always serializable.");
+        fs::write(path, transcript).expect("Cannot write transcript");
+    }
+
+    match result {
+        Ok(()) => true,
+        Err(InterpreterError::Syntax(err)) => {
+            println!("{:?}", err);
+            false
+        }
+        // Already reported by the interpreter.
+        Err(_) => false
+    }
 }
 
 fn repl() {
     let mut should_quit = false;
+    let mut buffer = ReplBuffer::new();
     while !should_quit {
         let mut text = String::new();
 
         print!("dmm> ");
         io::stdout().flush().expect("IO Error");
         match io::stdin().read_line(&mut text) {
+            Ok(0) | Err(_) => {
+                should_quit = true;
+            }
             Ok(_) => {
                 text = text.replace('\n', "");
-                interpret_text(text);
-            }
-            Err(_) => {
-                should_quit = true;
+                match buffer.handle(&text) {
+                    ReplAction::Execute(text) | ReplAction::Run(text) => {
+                        interpret_text(text, interpreter_builder());
+                    }
+                    ReplAction::BufferStarted => {
+                        println!("Collecting lines, :run runs them, :clear forgets them.");
+                    }
+                    ReplAction::Cleared => {
+                        println!("Buffer cleared.");
+                    }
+                    ReplAction::Buffered => {}
+                }
             }
         }
     }
 }
 
 fn main() -> Result<(), LexerError> {
-    if env::args().len() > 1 {
-        // Compile file.
-        let path = env::args().nth(1).unwrap();
-        let text = fs::read_to_string(path).unwrap();
-        if env::args().len() > 2 {
-            match env::args().nth(2).unwrap().as_str() {
+    if env::args().nth(1).as_deref() == Some("check") {
+        let mut json = false;
+        let mut path = None;
+        for arg in env::args().skip(2) {
+            match arg.as_str() {
+                "--json" => json = true,
+                _ if arg.starts_with("--") => usage_error(&format!("Unknown argument {}", arg)),
+                _ if path.is_some() => usage_error("dmm check takes one file"),
+                _ => path = Some(arg)
+            }
+        }
+        let path = path.unwrap_or_else(|| usage_error("Missing file to check, e.g. dmm check tests/basic/funny.dmm"));
+        let text = read_program(&path);
+        if !check_text(text, json) {
+            std::process::exit(1);
+        }
+    } else if env::args().len() > 1 || !io::stdin().is_terminal() {
+        // Compile file, or a program piped in like `echo ... | dmm`, nobody is there to talk to a REPL.
+        let mut args: Vec<String> = env::args().skip(1).collect();
+        // Flags go before or right after the path, everything after them is for the program's args().
+        let mut flags = take_flags(&mut args);
+        let path = if args.is_empty() { None } else { Some(args.remove(0)) };
+        flags.extend(take_flags(&mut args));
+
+        let mut builder = interpreter_builder().args(args);
+        let mut tokens = false;
+        let mut ast = false;
+        let mut no_envelope = false;
+        for flag in &flags {
+            builder = match flag.as_str() {
                 "--lexer" => {
-                    print_tokens(text);
+                    tokens = true;
+                    builder
                 }
                 "--ast" => {
-                    print_ast(text);
+                    ast = true;
+                    builder
                 }
-                _ => {
-                    interpret_text(text);
+                "--no-envelope" => {
+                    no_envelope = true;
+                    builder
                 }
+                "--traceback" => builder.traceback(true),
+                "--stats" => builder.humanoid_stats(true),
+                "--echo-input" => builder.echo_input(true),
+                "--block-scope" => builder.block_scoping(true),
+                "--env" => builder.environment_access(true),
+                "--dump-state" => builder.dump_state(true),
+                #[cfg(feature = "bignum")]
+                "--bignum" => builder.big_integers(true),
+                #[cfg(not(feature = "bignum"))]
+                "--bignum" => usage_error("--bignum needs dmm built with the bignum feature (cargo run --features bignum)"),
+                _ => usage_error(&format!("Unknown argument {}", flag))
+            };
+        }
+
+        let mut source: Box<dyn Read> = match path {
+            Some(path) => Box::new(File::open(&path).unwrap_or_else(|err| usage_error(&format!("Cannot read {}: {}", path, err)))),
+            None => Box::new(io::stdin())
+        };
+        if tokens || ast {
+            let mut text = String::new();
+            source.read_to_string(&mut text).expect("Cannot read program");
+            let lexer = || if no_envelope { Lexer::new_with_envelope(&text) } else { Lexer::new(&text) };
+            if tokens {
+                print_tokens(lexer());
+            }
+            if ast {
+                print_ast(lexer());
             }
         } else {
-            interpret_text(text);
+            let interpreter = builder.build_from_reader(source, no_envelope).expect("Cannot read program");
+            if !interpret(interpreter) {
+                std::process::exit(1);
+            }
         }
     } else {
         // REPL.
@@ -1,15 +1,32 @@
 use crate::lexer::{Lexer, LexerError, Token, Keyword};
+use serde::Serialize;
 use std::fmt::Display;
 use std::rc::Rc;
 
-#[derive(Debug, PartialEq, PartialOrd, Clone)]
+// Serializes to plain JSON: numbers, strings, booleans, arrays and null for -.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[serde(untagged)]
 pub enum Value {
     Integer(i32),
+    Float(f64),
     String(String),
     Boolean(bool),
+    List(Vec<Value>),
+    // Only produced in the big integer mode, for numbers beyond i32.
+    #[cfg(feature = "bignum")]
+    #[serde(serialize_with = "crate::bignum::serialize")]
+    BigInteger(num_bigint::BigInt),
+    // A function written as an expression, holding its ASTNode::Lambda.
+    #[serde(serialize_with = "serialize_function")]
+    Function(Rc<ASTNode>),
     None
 }
 
+// Functions have no JSON form, they are written the way they are printed.
+fn serialize_function<S: serde::Serializer>(function: &Rc<ASTNode>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&Value::Function(function.clone()))
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum CompareType {
     Equals,
@@ -17,18 +34,88 @@ pub enum CompareType {
     Greater
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub enum LogicType {
+    And,
+    Or
+}
+
+impl Value {
+    // Order for kleina/krasser: numbers by size, strings lexicographically.
+    // Everything else can only be compared for equality.
+    pub fn ordering(&self, other: &Value) -> Option<std::cmp::Ordering> {
+        match (self, other) {
+            (Value::Integer(left), Value::Integer(right)) => Some(left.cmp(right)),
+            (Value::Float(_), _) | (_, Value::Float(_)) => self.as_float()?.partial_cmp(&other.as_float()?),
+            (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+            #[cfg(feature = "bignum")]
+            (Value::BigInteger(_), _) | (_, Value::BigInteger(_)) => {
+                Some(crate::bignum::to_big(self).ok()?.cmp(&crate::bignum::to_big(other).ok()?))
+            },
+            _ => None
+        }
+    }
+
+    // Equality as `is` and `fall` see it: numbers are promoted like in ordering, so 1 is 1.0.
+    pub fn equals(&self, other: &Value) -> bool {
+        match (self.as_float(), other.as_float()) {
+            (Some(_), Some(_)) => self.ordering(other) == Some(std::cmp::Ordering::Equal),
+            _ => self == other
+        }
+    }
+
+    // The number as a float, None for anything that isn't a number.
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Integer(int) => Some(*int as f64),
+            Value::Float(float) => Some(*float),
+            #[cfg(feature = "bignum")]
+            Value::BigInteger(int) => int.to_string().parse().ok(),
+            _ => None
+        }
+    }
+}
+
 impl Display for Value {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Value::Integer(int) => {
                 write!(formatter, "{}", int)
             },
+            Value::Float(float) => {
+                // Whole floats keep their .0, so they read back in as floats.
+                if float.fract() == 0.0 && float.abs() < 1e16 {
+                    write!(formatter, "{:.1}", float)
+                } else {
+                    write!(formatter, "{}", float)
+                }
+            },
             Value::String(string) => {
                 write!(formatter, "{}", string)
             },
             Value::Boolean(b) => {
                 write!(formatter, "{}", if *b { ":)" } else { ":("} )
             },
+            Value::List(values) => {
+                write!(formatter, "/")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(formatter, ", ")?;
+                    }
+                    write!(formatter, "{}", value)?;
+                }
+                write!(formatter, "\\")
+            },
+            #[cfg(feature = "bignum")]
+            Value::BigInteger(int) => {
+                write!(formatter, "{}", int)
+            },
+            Value::Function(function) => {
+                match &**function {
+                    ASTNode::Lambda {parameters, ..} => write!(formatter, "funny({})", parameters.join(" ")),
+                    _ => write!(formatter, "funny")
+                }
+            },
             Value::None => {
                 write!(formatter, "-")
             }
@@ -51,28 +138,81 @@ pub enum ASTNode {
     Value {
         value: Value
     },
+    List {
+        elements: Vec<ASTNode>
+    },
     FunctionCall {
         function: Rc<ASTNode>,
         parameters: Vec<ASTNode>
     },
     FunctionDeclaration {
         name: String,
+        parameters: Vec<String>,
+        execution_block: Rc<ASTNode>,
+        // Character offset of the name in the source, for diagnostics.
+        offset: usize
+    },
+    // `funny(a) avo ... cado` without a name, evaluating to a Value::Function.
+    Lambda {
         parameters: Vec<String>,
         execution_block: Rc<ASTNode>
     },
+    // `wenn c avo ... cado sonst avo ... cado`, where the sonst block may also be another wenn.
     If {
         condition: Rc<ASTNode>,
-        execution: Rc<ASTNode>
+        execution: Rc<ASTNode>,
+        else_execution: Option<Rc<ASTNode>>
+    },
+    // `pruef subject` runs the block of the first `fall` whose value equals the subject,
+    // or the `sonst` block if none does.
+    Match {
+        subject: Rc<ASTNode>,
+        arms: Vec<(ASTNode, ASTNode)>,
+        default: Option<Rc<ASTNode>>
     },
+    // Loops may be named with `namens <label>`, so `raus <label>` can leave them from within inner loops.
     Loop {
         condition: Rc<ASTNode>,
-        execution: Rc<ASTNode>
+        execution: Rc<ASTNode>,
+        label: Option<String>
+    },
+    DoLoop {
+        execution: Rc<ASTNode>,
+        condition: Rc<ASTNode>,
+        label: Option<String>
+    },
+    Repeat {
+        count: Rc<ASTNode>,
+        execution: Rc<ASTNode>,
+        label: Option<String>
+    },
+    Break {
+        label: Option<String>
+    },
+    // `nochmal` skips the rest of the body, `nochmal <label>` that of the named loop.
+    Continue {
+        label: Option<String>
     },
     Compare {
         left: Rc<ASTNode>,
         right: Rc<ASTNode>,
         compare_type: CompareType
     },
+    // `a kleina b kleina c`, meaning `a kleina b und b kleina c` with b evaluated once.
+    CompareChain {
+        operands: Vec<ASTNode>,
+        compare_types: Vec<CompareType>
+    },
+    LogicOp {
+        left: Rc<ASTNode>,
+        right: Rc<ASTNode>,
+        op: LogicType
+    },
+    // `xs[0]`, an element of a list or a character of a string.
+    Index {
+        target: Rc<ASTNode>,
+        index: Rc<ASTNode>
+    },
     Block {
         children: Vec<ASTNode>
     },
@@ -83,29 +223,192 @@ pub enum ASTNode {
     Return {
         expression: Rc<ASTNode>,
     },
+    // A statement with the comments written above it, only produced by Parser::with_comments.
+    // Comments after the last statement of a block or program trail that statement.
+    Commented {
+        leading_comments: Vec<String>,
+        statement: Rc<ASTNode>,
+        trailing_comments: Vec<String>
+    },
     Variable {
-        name: String
+        name: String,
+        // Character offset of the name in the source, for diagnostics.
+        offset: usize
     },
     NoOp
 }
 
+impl ASTNode {
+    // How many nodes deep the tree goes below and including this one, a lone value is 1.
+    pub fn height(&self) -> usize {
+        1 + self.children().into_iter().map(ASTNode::height).max().unwrap_or(0)
+    }
+
+    // The nodes directly below this one, in source order.
+    pub fn children(&self) -> Vec<&ASTNode> {
+        match self {
+            ASTNode::UnaryOp {expression, ..} | ASTNode::Return {expression} |
+            ASTNode::Commented {statement: expression, ..} => vec![expression],
+            ASTNode::BinOp {left, right, ..} | ASTNode::Compare {left, right, ..} | ASTNode::LogicOp {left, right, ..} |
+            ASTNode::Assign {left, right} | ASTNode::Index {target: left, index: right} => vec![left, right],
+            ASTNode::List {elements: children} | ASTNode::Block {children} |
+            ASTNode::CompareChain {operands: children, ..} => children.iter().collect(),
+            ASTNode::FunctionCall {function, parameters} => {
+                let mut children: Vec<&ASTNode> = vec![function];
+                children.extend(parameters.iter());
+                children
+            },
+            ASTNode::FunctionDeclaration {execution_block, ..} | ASTNode::Lambda {execution_block, ..} => vec![execution_block],
+            ASTNode::If {condition, execution, else_execution} => {
+                let mut children: Vec<&ASTNode> = vec![condition, execution];
+                children.extend(else_execution.as_deref());
+                children
+            },
+            ASTNode::Loop {condition, execution, ..} => vec![condition, execution],
+            ASTNode::DoLoop {execution, condition, ..} => vec![execution, condition],
+            ASTNode::Repeat {count, execution, ..} => vec![count, execution],
+            ASTNode::Match {subject, arms, default} => {
+                let mut children: Vec<&ASTNode> = vec![subject];
+                children.extend(arms.iter().flat_map(|(value, execution)| [value, execution]));
+                children.extend(default.as_deref());
+                children
+            },
+            ASTNode::Value {..} | ASTNode::Variable {..} | ASTNode::Break {..} | ASTNode::Continue {..} | ASTNode::NoOp => vec![]
+        }
+    }
+
+    // Rebuilds this node with `f` applied to each of its direct children.
+    pub fn map_children(self, f: &dyn Fn(ASTNode) -> ASTNode) -> ASTNode {
+        let map = |node: Rc<ASTNode>| Rc::new(f(Rc::unwrap_or_clone(node)));
+        match self {
+            ASTNode::UnaryOp {expression, token} => ASTNode::UnaryOp {expression: map(expression), token},
+            ASTNode::BinOp {left, right, token} => ASTNode::BinOp {left: map(left), right: map(right), token},
+            ASTNode::List {elements} => ASTNode::List {elements: elements.into_iter().map(f).collect()},
+            ASTNode::Index {target, index} => ASTNode::Index {target: map(target), index: map(index)},
+            ASTNode::FunctionCall {function, parameters} => ASTNode::FunctionCall {
+                function: map(function),
+                parameters: parameters.into_iter().map(f).collect()
+            },
+            ASTNode::FunctionDeclaration {name, parameters, execution_block, offset} => ASTNode::FunctionDeclaration {
+                name,
+                parameters,
+                execution_block: map(execution_block),
+                offset
+            },
+            ASTNode::Lambda {parameters, execution_block} => ASTNode::Lambda {parameters, execution_block: map(execution_block)},
+            ASTNode::If {condition, execution, else_execution} => ASTNode::If {
+                condition: map(condition),
+                execution: map(execution),
+                else_execution: else_execution.map(map)
+            },
+            ASTNode::Loop {condition, execution, label} => ASTNode::Loop {condition: map(condition), execution: map(execution), label},
+            ASTNode::DoLoop {execution, condition, label} => ASTNode::DoLoop {execution: map(execution), condition: map(condition), label},
+            ASTNode::Repeat {count, execution, label} => ASTNode::Repeat {count: map(count), execution: map(execution), label},
+            ASTNode::Match {subject, arms, default} => ASTNode::Match {
+                subject: map(subject),
+                arms: arms.into_iter().map(|(value, execution)| (f(value), f(execution))).collect(),
+                default: default.map(map)
+            },
+            ASTNode::Compare {left, right, compare_type} => ASTNode::Compare {left: map(left), right: map(right), compare_type},
+            ASTNode::CompareChain {operands, compare_types} => ASTNode::CompareChain {
+                operands: operands.into_iter().map(f).collect(),
+                compare_types
+            },
+            ASTNode::LogicOp {left, right, op} => ASTNode::LogicOp {left: map(left), right: map(right), op},
+            ASTNode::Block {children} => ASTNode::Block {children: children.into_iter().map(f).collect()},
+            ASTNode::Assign {left, right} => ASTNode::Assign {left: map(left), right: map(right)},
+            ASTNode::Return {expression} => ASTNode::Return {expression: map(expression)},
+            ASTNode::Commented {leading_comments, statement, trailing_comments} => ASTNode::Commented {leading_comments, statement: map(statement), trailing_comments},
+            node @ (ASTNode::Value {..} | ASTNode::Variable {..} | ASTNode::Break {..} | ASTNode::Continue {..} | ASTNode::NoOp) => node
+        }
+    }
+}
+
+pub type Pass = Box<dyn Fn(ASTNode) -> ASTNode>;
+
+// Runs the passes in order between parsing and interpretation.
+// Each pass rewrites single nodes and is applied bottom-up to the whole tree,
+// so a node's children have already been rewritten when the pass sees it.
+pub fn transform(node: ASTNode, passes: &[Pass]) -> ASTNode {
+    passes.iter().fold(node, |node, pass| rewrite(node, pass.as_ref()))
+}
+
+fn rewrite(node: ASTNode, pass: &dyn Fn(ASTNode) -> ASTNode) -> ASTNode {
+    pass(node.map_children(&|child| rewrite(child, pass)))
+}
+
+// Example pass: computes integer arithmetic on literals ahead of time.
+// Overflow and division by zero are left for the interpreter to run into.
+pub fn fold_constants(node: ASTNode) -> ASTNode {
+    let folded = match &node {
+        ASTNode::BinOp {left, right, token} => match (&**left, &**right) {
+            (ASTNode::Value {value: Value::Integer(left)}, ASTNode::Value {value: Value::Integer(right)}) => match token {
+                Token::Plus => left.checked_add(*right),
+                Token::Minus => left.checked_sub(*right),
+                Token::Multiply => left.checked_mul(*right),
+                Token::Divide => left.checked_div(*right),
+                Token::Modulo => left.checked_rem(*right),
+                _ => None
+            },
+            _ => None
+        },
+        ASTNode::UnaryOp {expression, token} => match (&**expression, token) {
+            (ASTNode::Value {value: Value::Integer(value)}, Token::Plus) => Some(*value),
+            (ASTNode::Value {value: Value::Integer(value)}, Token::Minus) => value.checked_neg(),
+            _ => None
+        },
+        _ => None
+    };
+    match folded {
+        Some(value) => ASTNode::Value {value: Value::Integer(value)},
+        None => node
+    }
+}
+
 pub struct Parser {
     current_token: Token,
-    lexer: Lexer
+    lexer: Lexer,
+    // Comments read since the last statement started, waiting for the next one.
+    comments: Vec<String>,
+    // A statement starting with a name that turned out not to be an assignment,
+    // the name is the first factor of an expression then.
+    statement_variable: Option<ASTNode>
 }
 
 impl Parser {
     pub fn new(lexer: Lexer) -> Parser {
         let mut parser = Parser {
             lexer,
-            current_token: Token::EOF
+            current_token: Token::EOF,
+            comments: Vec::new(),
+            statement_variable: None
         };
-        parser.current_token = parser.lexer.get_next_token().unwrap_or(Token::EOF);
-        return parser;
+        parser.current_token = parser.next_token().unwrap_or(Token::EOF);
+        parser
+    }
+
+    // Keeps the comments in the tree, attached to the statement below them.
+    // For tooling like formatters, the interpreter doesn't need them.
+    pub fn with_comments(lexer: Lexer) -> Parser {
+        Parser::new(lexer.keep_comments())
+    }
+
+    fn next_token(&mut self) -> Result<Token, LexerError> {
+        loop {
+            match self.lexer.get_next_token()? {
+                Token::Comment(comment) => self.comments.push(comment),
+                token => return Ok(token)
+            }
+        }
+    }
+
+    // The character offset where the current token starts.
+    pub fn position(&self) -> usize {
+        self.lexer.token_start()
     }
 
     fn consume_token(&mut self) -> Result<(), LexerError> {
-        self.current_token = self.lexer.get_next_token()?;
+        self.current_token = self.next_token()?;
         Ok(())
     }
 
@@ -122,19 +425,27 @@ impl Parser {
     }
 
     fn function_call_or_variable(&mut self) -> Result<ASTNode, LexerError> {
-        let variable = self.variable()?;
-        if self.current_token == Token::ParentheseOpen {
-            Ok(self.functioncall_statement(variable)?)
-        } else {
-            Ok(variable)
-        }
+        let variable = match self.statement_variable.take() {
+            Some(variable) => variable,
+            None => self.variable()?
+        };
+        self.calls(variable)
     }
 
     fn factor(&mut self) -> Result<ASTNode, LexerError> {
-        // FACTOR := +|- FACTOR | integer | (EXPR) | string | boolean | VARIABLE
-        if Token::Plus == self.current_token || Token::Minus == self.current_token {
+        // FACTOR := +|-|nicht FACTOR | integer | float | (EXPR) CALL* | LAMBDA CALL* | string CALL* | boolean | LIST CALL* | VARIABLE CALL*
+        if self.statement_variable.is_some() {
+            return self.function_call_or_variable();
+        }
+        if Token::Plus == self.current_token || Token::Minus == self.current_token || Token::ReservedKeyword(Keyword::Not) == self.current_token {
             let unary_token = self.current_token.clone();
             self.consume_token()?;
+            // A minus directly before a literal belongs to it, which makes room for -2147483648.
+            if let (Token::Minus, Token::Integer(value)) = (&unary_token, &self.current_token) {
+                let value = Parser::integer_literal(-i64::from(*value))?;
+                self.consume_token()?;
+                return Ok(ASTNode::Value {value});
+            }
             let node = ASTNode::UnaryOp {
                 expression: Rc::new(self.factor()?),
                 token: unary_token
@@ -144,7 +455,13 @@ impl Parser {
 
         if let Token::Integer(value) = self.current_token {
             let node = ASTNode::Value {
-                value: Value::Integer(value as i32)
+                value: Parser::integer_literal(i64::from(value))?
+            };
+            self.consume_token()?;
+            Ok(node)
+        } else if let Token::Float(value) = self.current_token {
+            let node = ASTNode::Value {
+                value: Value::Float(value)
             };
             self.consume_token()?;
             Ok(node)
@@ -152,28 +469,65 @@ impl Parser {
             self.consume(Token::ParentheseOpen)?;
             let node = self.expr()?;
             self.consume(Token::ParentheseClose)?;
-            Ok(node)
+            self.calls(node)
+        } else if Token::ReservedKeyword(Keyword::Function) == self.current_token {
+            let node = self.lambda()?;
+            self.calls(node)
         }  else if let Token::String(string) = &self.current_token {
             let node = ASTNode::Value {
                 value: Value::String(string.clone())
             };
             self.consume_token()?;
-            Ok(node)
+            self.calls(node)
         } else if let Token::Boolean(b) = &self.current_token {
             let node = ASTNode::Value {
                 value: Value::Boolean(*b)
             };
             self.consume_token()?;
             Ok(node)
+        } else if Token::Divide == self.current_token {
+            let node = self.list()?;
+            self.calls(node)
+        } else if Parser::compare_type(&self.current_token).is_some() {
+            Err(LexerError::UnexpectedToken {
+                found: self.current_token.clone(),
+                expected: "value to compare".to_string()
+            })
         } else {
             Ok(self.function_call_or_variable()?)
         }
     } 
 
+    fn integer_literal(value: i64) -> Result<Value, LexerError> {
+        i32::try_from(value)
+            .map(Value::Integer)
+            .map_err(|_| LexerError::InvalidSyntax(format!("Number too large: {}", value)))
+    }
+
+    fn list(&mut self) -> Result<ASTNode, LexerError> {
+        // LIST := / (EXPR (, EXPR)*)? \
+        self.consume(Token::Divide)?;
+        let mut elements : Vec<ASTNode> = Vec::new();
+        if self.current_token != Token::ListClose {
+            loop {
+                elements.push(self.expr()?);
+                if self.current_token != Token::Comma {
+                    break;
+                } else {
+                    self.consume(Token::Comma)?;
+                }
+            }
+        }
+        self.consume(Token::ListClose)?;
+        Ok(ASTNode::List {
+            elements
+        })
+    }
+
     fn term(&mut self) -> Result<ASTNode, LexerError> {
-        // TERM := FACTOR ((MUL|DIV)FACTOR)*
+        // TERM := FACTOR ((MUL|DIV|MOD)FACTOR)*
         let mut node = self.factor()?;
-        while self.current_token == Token::Multiply || self.current_token == Token::Divide { 
+        while self.current_token == Token::Multiply || self.current_token == Token::Divide || self.current_token == Token::Modulo { 
             let operator_token = self.current_token.clone();
             self.consume_token()?;
             node = ASTNode::BinOp {
@@ -182,31 +536,11 @@ impl Parser {
                 token: operator_token
             };
         }
-        while let Token::ReservedKeyword(keyword) = self.current_token  { 
-            let compare_type = match keyword {
-                Keyword::Equals => {
-                    CompareType::Equals
-                },
-                Keyword::Less => {
-                    CompareType::Less
-                },
-                Keyword::Greater => {
-                    CompareType::Greater
-                },
-                _ => {break;}
-            };
-            self.consume_token()?;
-            node = ASTNode::Compare {
-                left: Rc::new(node), 
-                right: Rc::new(self.factor()?),
-                compare_type
-            };
-        }
         Ok(node)
     }
 
-    fn expr(&mut self) -> Result<ASTNode, LexerError>{
-        // EXPR := TERM ((PLUS|MINUS)TERM)*
+    fn arithmetic(&mut self) -> Result<ASTNode, LexerError>{
+        // ARITHMETIC := TERM ((PLUS|MINUS)TERM)*
         let mut node = self.term()?;
 
         while self.current_token == Token::Plus || self.current_token == Token::Minus {
@@ -222,6 +556,77 @@ impl Parser {
         Ok(node)
     }
 
+    fn compare_type(token: &Token) -> Option<CompareType> {
+        match token {
+            Token::ReservedKeyword(Keyword::Equals) => Some(CompareType::Equals),
+            Token::ReservedKeyword(Keyword::Less) => Some(CompareType::Less),
+            Token::ReservedKeyword(Keyword::Greater) => Some(CompareType::Greater),
+            _ => None
+        }
+    }
+
+    fn comparison(&mut self) -> Result<ASTNode, LexerError> {
+        // COMPARISON := ARITHMETIC ((is|kleina|krasser) ARITHMETIC)*
+        // Chains read like in maths (and Python): `a kleina b is c` means
+        // `a kleina b und b is c`, never comparing the boolean `a kleina b` with c.
+        let mut operands = vec![self.arithmetic()?];
+        let mut compare_types = Vec::new();
+        while let Some(compare_type) = Parser::compare_type(&self.current_token) {
+            self.consume_token()?;
+            compare_types.push(compare_type);
+            operands.push(self.arithmetic()?);
+        }
+        Ok(match operands.len() {
+            1 => operands.remove(0),
+            2 => {
+                let right = operands.remove(1);
+                ASTNode::Compare {
+                    left: Rc::new(operands.remove(0)),
+                    right: Rc::new(right),
+                    compare_type: compare_types.remove(0)
+                }
+            },
+            _ => ASTNode::CompareChain {
+                operands,
+                compare_types
+            }
+        })
+    }
+
+    fn conjunction(&mut self) -> Result<ASTNode, LexerError> {
+        // CONJUNCTION := COMPARISON (und COMPARISON)*
+        let mut node = self.comparison()?;
+        while self.current_token == Token::ReservedKeyword(Keyword::And) {
+            self.consume_token()?;
+            node = ASTNode::LogicOp {
+                left: Rc::new(node),
+                right: Rc::new(self.comparison()?),
+                op: LogicType::And
+            };
+        }
+        Ok(node)
+    }
+
+    fn disjunction(&mut self) -> Result<ASTNode, LexerError> {
+        // DISJUNCTION := CONJUNCTION (oda CONJUNCTION)*
+        let mut node = self.conjunction()?;
+        while self.current_token == Token::ReservedKeyword(Keyword::Or) {
+            self.consume_token()?;
+            node = ASTNode::LogicOp {
+                left: Rc::new(node),
+                right: Rc::new(self.conjunction()?),
+                op: LogicType::Or
+            };
+        }
+        Ok(node)
+    }
+
+    fn expr(&mut self) -> Result<ASTNode, LexerError> {
+        // EXPR := DISJUNCTION
+        // Binding from loose to tight: oda, und, comparisons, +/-, *//, unary.
+        self.disjunction()
+    }
+
     fn empty(&mut self) -> ASTNode {
         ASTNode::NoOp {}
     }
@@ -229,9 +634,11 @@ impl Parser {
     fn variable(&mut self) -> Result<ASTNode, LexerError> {
         match self.current_token.clone() {
             Token::ID{string} => {
+                let offset = self.position();
                 self.consume_token()?;
                 let node = ASTNode::Variable {
-                    name: string.clone()
+                    name: string.clone(),
+                    offset
                 };
                 
                 Ok(node)
@@ -278,29 +685,150 @@ impl Parser {
         )
     }
 
+    // Calls and indexes directly on a value, as in (funny(a) avo wirf a cado)(1) or xs[0].
+    fn calls(&mut self, mut node: ASTNode) -> Result<ASTNode, LexerError> {
+        // CALL := (PARAMETERS) | [EXPR]
+        loop {
+            if self.current_token == Token::ParentheseOpen {
+                node = self.functioncall_statement(node)?;
+            } else if self.current_token == Token::IndexOpen {
+                self.consume_token()?;
+                let index = self.expr()?;
+                self.consume(Token::IndexClose)?;
+                node = ASTNode::Index {target: Rc::new(node), index: Rc::new(index)};
+            } else {
+                return Ok(node);
+            }
+        }
+    }
+
+    fn parameter_names(&mut self) -> Result<Vec<String>, LexerError> {
+        self.consume(Token::ParentheseOpen)?;
+        let mut parameters: Vec<String> = Vec::new();
+        if self.current_token != Token::ParentheseClose {
+            while let Token::ID{string} = self.current_token.clone() {
+                self.consume_token()?;
+                parameters.push(string.clone());
+            } 
+        }
+        self.consume(Token::ParentheseClose)?;
+        Ok(parameters)
+    }
+
+    fn lambda(&mut self) -> Result<ASTNode, LexerError> {
+        // LAMBDA := funny (ID*) BLOCK
+        self.consume(Token::ReservedKeyword(Keyword::Function))?;
+        Ok(ASTNode::Lambda {
+            parameters: self.parameter_names()?,
+            execution_block: Rc::new(self.inner_block_statement()?)
+        })
+    }
+
+    fn optional_name(&mut self) -> Result<Option<String>, LexerError> {
+        if let Token::ID {string} = &self.current_token {
+            let name = string.clone();
+            self.consume_token()?;
+            Ok(Some(name))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn loop_label(&mut self) -> Result<Option<String>, LexerError> {
+        // LABEL := (namens ID)?
+        if self.current_token != Token::ReservedKeyword(Keyword::Label) {
+            return Ok(None);
+        }
+        self.consume_token()?;
+        match self.optional_name()? {
+            Some(name) => Ok(Some(name)),
+            None => Err(LexerError::UnexpectedToken {
+                found: self.current_token.clone(),
+                expected: "ID for the loop label".to_string()
+            })
+        }
+    }
+
+    fn if_statement(&mut self) -> Result<ASTNode, LexerError> {
+        // IF := wenn EXPR BLOCK EndLine* (sonst (BLOCK | IF))?
+        let condition = Rc::new(self.expr()?);
+        let execution = Rc::new(self.inner_block_statement()?);
+        let mut else_execution = None;
+        if self.skip_blank_lines_before(Token::ReservedKeyword(Keyword::Default))? {
+            self.consume_token()?;
+            else_execution = Some(Rc::new(if self.current_token == Token::ReservedKeyword(Keyword::If) {
+                self.consume_token()?;
+                self.if_statement()?
+            } else {
+                self.inner_block_statement()?
+            }));
+        }
+        Ok(ASTNode::If {condition, execution, else_execution})
+    }
+
+    fn match_statement(&mut self) -> Result<ASTNode, LexerError> {
+        // MATCH := pruef EXPR avo (fall EXPR BLOCK)* (sonst BLOCK)? cado
+        let subject = Rc::new(self.expr()?);
+        self.skip_blank_lines()?;
+        self.consume(Token::ReservedKeyword(Keyword::Avo))?;
+        self.skip_blank_lines()?;
+        let mut arms = Vec::new();
+        while self.current_token == Token::ReservedKeyword(Keyword::Case) {
+            self.consume_token()?;
+            let value = self.expr()?;
+            arms.push((value, self.inner_block_statement()?));
+            self.skip_blank_lines()?;
+        }
+        let mut default = None;
+        if self.current_token == Token::ReservedKeyword(Keyword::Default) {
+            self.consume_token()?;
+            default = Some(Rc::new(self.inner_block_statement()?));
+            self.skip_blank_lines()?;
+        }
+        self.consume(Token::ReservedKeyword(Keyword::Cado))?;
+        Ok(ASTNode::Match {subject, arms, default})
+    }
+
     fn statement(&mut self) -> Result<ASTNode, LexerError> {
+        let mut leading_comments = std::mem::take(&mut self.comments);
+        let statement = self.bare_statement()?;
+        if leading_comments.is_empty() {
+            Ok(statement)
+        } else if statement == ASTNode::NoOp {
+            // Blank lines pass the comments on to the next statement.
+            leading_comments.append(&mut self.comments);
+            self.comments = leading_comments;
+            Ok(statement)
+        } else {
+            Ok(ASTNode::Commented {leading_comments, statement: Rc::new(statement), trailing_comments: Vec::new()})
+        }
+    }
+
+    fn bare_statement(&mut self) -> Result<ASTNode, LexerError> {
         Ok(match &self.current_token {
             Token::ID{string: _} => {
                 let left = self.variable()?;
                 if self.current_token == Token::Assign {
                     self.assignment_statement(left)?
-                } else if self.current_token == Token::ParentheseOpen {
-                    self.functioncall_statement(left)?
                 } else {
-                    self.empty()
+                    // A call like f(x) or any other expression, as in `a + 1`.
+                    self.statement_variable = Some(left);
+                    self.expr()?
                 }
             },
             Token::ReservedKeyword(keyword) => {
                 match keyword {
                     Keyword::If | Keyword::Equals => {
                         self.consume_token()?;
-                        ASTNode::If {
-                            condition: Rc::new(self.expr()?),
-                            execution: Rc::new(self.inner_block_statement()?)
-                        }
+                        self.if_statement()?
+                    },
+                    Keyword::Match => {
+                        self.consume_token()?;
+                        self.match_statement()?
                     },
                     Keyword::Function => {
                         self.consume_token()?;
+                        let offset = self.position();
                         let func_name = match &self.current_token {
                             Token::ID {string} => {
                                 string.clone()
@@ -308,26 +836,53 @@ impl Parser {
                             _ => {return Err(LexerError::UnexpectedToken{expected: "ID for FunctionName".to_string(), found: self.current_token.clone()});}
                         };
                         self.consume_token()?;
-                        self.consume(Token::ParentheseOpen)?;
-                        let mut parameters: Vec<String> = Vec::new();
-                        if self.current_token != Token::ParentheseClose {
-                            while let Token::ID{string} = self.current_token.clone() {
-                                self.consume_token()?;
-                                parameters.push(string.clone());
-                            } 
-                        }
-                        self.consume(Token::ParentheseClose)?;
+                        let parameters = self.parameter_names()?;
                         ASTNode::FunctionDeclaration {
                             name: func_name.clone(),
                             parameters,
-                            execution_block: Rc::new(self.inner_block_statement()?)
+                            execution_block: Rc::new(self.inner_block_statement()?),
+                            offset
                         }
                     },
                     Keyword::Loop => {
                         self.consume_token()?;
+                        let label = self.loop_label()?;
                         ASTNode::Loop {
                             condition: Rc::new(self.expr()?),
-                            execution: Rc::new(self.inner_block_statement()?)
+                            execution: Rc::new(self.inner_block_statement()?),
+                            label
+                        }
+                    },
+                    Keyword::Repeat => {
+                        self.consume_token()?;
+                        let label = self.loop_label()?;
+                        ASTNode::Repeat {
+                            count: Rc::new(self.expr()?),
+                            execution: Rc::new(self.inner_block_statement()?),
+                            label
+                        }
+                    },
+                    Keyword::DoLoop => {
+                        self.consume_token()?;
+                        let label = self.loop_label()?;
+                        let execution = Rc::new(self.inner_block_statement()?);
+                        self.consume(Token::ReservedKeyword(Keyword::DoLoopCondition))?;
+                        ASTNode::DoLoop {
+                            execution,
+                            condition: Rc::new(self.expr()?),
+                            label
+                        }
+                    },
+                    Keyword::Break => {
+                        self.consume_token()?;
+                        ASTNode::Break {
+                            label: self.optional_name()?
+                        }
+                    },
+                    Keyword::Continue => {
+                        self.consume_token()?;
+                        ASTNode::Continue {
+                            label: self.optional_name()?
                         }
                     },
                     Keyword::AssignPrefix => {
@@ -346,9 +901,17 @@ impl Parser {
                             expression: Rc::new(self.expr()?)
                         }
                     },
+                    Keyword::Not => {
+                        self.expr()?
+                    },
                     _ => {self.empty()}
                 }
             },
+            // An expression on its own, e.g. the result at the end of a program.
+            Token::Integer(_) | Token::Float(_) | Token::String(_) | Token::Boolean(_) |
+            Token::ParentheseOpen | Token::Divide | Token::Plus | Token::Minus => {
+                self.expr()?
+            },
             _ => {self.empty()}
         }) 
     }
@@ -363,8 +926,27 @@ impl Parser {
                 nodes.push(statement);
             }
         }
+        self.attach_trailing_comments(&mut nodes);
 
-        return Ok(nodes);
+        Ok(nodes)
+    }
+
+    // Comments no statement followed belong to the last one before them.
+    fn attach_trailing_comments(&mut self, nodes: &mut [ASTNode]) {
+        let Some(last) = nodes.last_mut() else {
+            return;
+        };
+        if self.comments.is_empty() {
+            return;
+        }
+        let comments = std::mem::take(&mut self.comments);
+        *last = match std::mem::replace(last, ASTNode::NoOp) {
+            ASTNode::Commented {leading_comments, statement, mut trailing_comments} => {
+                trailing_comments.extend(comments);
+                ASTNode::Commented {leading_comments, statement, trailing_comments}
+            },
+            statement => ASTNode::Commented {leading_comments: Vec::new(), statement: Rc::new(statement), trailing_comments: comments}
+        };
     }
 
     fn inner_block_statement(&mut self) -> Result<ASTNode, LexerError>{
@@ -390,23 +972,73 @@ impl Parser {
         Ok(block_node)
     }
 
+    fn skip_blank_lines(&mut self) -> Result<(), LexerError> {
+        while self.current_token == Token::EndLine {
+            self.consume_token()?;
+        }
+        Ok(())
+    }
+
+    // Skips blank lines if the token follows them, otherwise they are given back
+    // and still end the statement before.
+    fn skip_blank_lines_before(&mut self, token: Token) -> Result<bool, LexerError> {
+        let mut blank_lines = 0;
+        while self.current_token == Token::EndLine {
+            self.consume_token()?;
+            blank_lines += 1;
+        }
+        if self.current_token == token {
+            return Ok(true);
+        }
+        if blank_lines > 0 {
+            let mut tokens = vec![Token::EndLine; blank_lines - 1];
+            tokens.push(std::mem::replace(&mut self.current_token, Token::EndLine));
+            self.lexer.give_back(tokens);
+        }
+        Ok(false)
+    }
+
     fn program(&mut self) -> Result<ASTNode, LexerError> {
+        // PROGRAM := EndLine* hallo EndLine+ STATEMENT_LIST reicht dann auch mal EndLine*
+        // Blank lines between statements and before the farewell become NoOps and are dropped.
+        self.skip_blank_lines()?;
         self.consume(Token::ReservedKeyword(Keyword::Greeting))?;
         self.consume(Token::EndLine)?;
-        let node = self.block_statement()?;
+        self.skip_blank_lines()?;
+        let mut node = self.block_statement()?;
         self.consume(Token::ReservedKeyword(Keyword::Farewell))?;
+        self.skip_blank_lines()?;
+        if let ASTNode::Block {children} = &mut node {
+            self.attach_trailing_comments(children);
+        }
         Ok(node)
     }
 
-    pub fn parse(&mut self) -> Result<ASTNode, LexerError>{
-        let program = self.program()?;
+    fn end_of_input(&mut self, node: ASTNode) -> Result<ASTNode, LexerError> {
         if self.current_token != Token::EOF {
             Err(LexerError::UnexpectedToken {
                 found: self.current_token.clone(),
                 expected: "EOF".to_string()
             })
         } else {
-            Ok(program)
+            Ok(node)
         }
     }
+
+    pub fn parse(&mut self) -> Result<ASTNode, LexerError>{
+        let program = self.program()?;
+        self.end_of_input(program)
+    }
+
+    // Parses a lone expression like `1 + 2`, without hallo/reicht dann auch mal.
+    pub fn parse_expression(&mut self) -> Result<ASTNode, LexerError> {
+        let expression = self.expr()?;
+        self.end_of_input(expression)
+    }
+
+    // Parses a lone statement like `machma x uf 1`, without hallo/reicht dann auch mal.
+    pub fn parse_statement(&mut self) -> Result<ASTNode, LexerError> {
+        let statement = self.statement()?;
+        self.end_of_input(statement)
+    }
 }
\ No newline at end of file
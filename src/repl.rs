@@ -0,0 +1,60 @@
+// What the REPL should do with an entered line.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ReplAction {
+    // Interpret the line right away.
+    Execute(String),
+    // Interpret the whole buffered program.
+    Run(String),
+    Buffered,
+    BufferStarted,
+    Cleared
+}
+
+// Collects lines of a program across REPL inputs.
+// `:buffer` starts collecting, `:run` runs everything so far, `:clear` forgets it.
+#[derive(Debug, Default)]
+pub struct ReplBuffer {
+    buffering: bool,
+    lines: Vec<String>
+}
+
+impl ReplBuffer {
+    pub fn new() -> Self {
+        ReplBuffer::default()
+    }
+
+    // The buffered lines wrapped with greeting and farewell.
+    pub fn program(&self) -> String {
+        let mut program = String::from("hallo\n");
+        for line in &self.lines {
+            program.push_str(line);
+            program.push('\n');
+        }
+        program.push_str("reicht dann auch mal");
+        program
+    }
+
+    pub fn handle(&mut self, line: &str) -> ReplAction {
+        match line.trim() {
+            ":buffer" => {
+                self.buffering = true;
+                ReplAction::BufferStarted
+            },
+            ":run" => {
+                ReplAction::Run(self.program())
+            },
+            ":clear" => {
+                self.lines.clear();
+                ReplAction::Cleared
+            },
+            _ => {
+                if self.buffering {
+                    self.lines.push(line.to_string());
+                    ReplAction::Buffered
+                } else {
+                    ReplAction::Execute(line.to_string())
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+// Everything that made a humanoid session turn out the way it did.
+// The random draws all come from the seed, the rest is listed in order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transcript {
+    pub seed: u64,
+    pub events: Vec<Event>
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Event {
+    // A line read from the input, None if reading failed.
+    Input(Option<String>),
+    // A dramatic pause, in nanoseconds.
+    Sleep(u64),
+    // Whether the worker's question cooldown was over, which depends on the clock.
+    Cooldown(bool)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Live,
+    Recording,
+    Replaying
+}
+
+#[derive(Debug, Default)]
+struct Tape {
+    transcript: Transcript,
+    // The next event to replay.
+    position: usize
+}
+
+// Shared by the console and the humanoids, so the events end up in one transcript.
+#[derive(Debug, Clone)]
+pub struct Session {
+    mode: Mode,
+    tape: Rc<RefCell<Tape>>
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session::live()
+    }
+}
+
+impl Session {
+    pub fn live() -> Self {
+        Session { mode: Mode::Live, tape: Rc::default() }
+    }
+
+    pub fn recording() -> Self {
+        Session { mode: Mode::Recording, tape: Rc::default() }
+    }
+
+    pub fn replaying(transcript: Transcript) -> Self {
+        Session { mode: Mode::Replaying, tape: Rc::new(RefCell::new(Tape { transcript, position: 0 })) }
+    }
+
+    // The seed to use instead of a new one when replaying.
+    pub fn replay_seed(&self) -> Option<u64> {
+        match self.mode {
+            Mode::Replaying => Some(self.tape.borrow().transcript.seed),
+            _ => None
+        }
+    }
+
+    pub fn start(&self, seed: u64) {
+        if self.mode == Mode::Recording {
+            self.tape.borrow_mut().transcript.seed = seed;
+        }
+    }
+
+    pub fn transcript(&self) -> Transcript {
+        self.tape.borrow().transcript.clone()
+    }
+
+    fn record(&self, event: Event) {
+        if self.mode == Mode::Recording {
+            self.tape.borrow_mut().transcript.events.push(event);
+        }
+    }
+
+    fn replay(&self) -> Event {
+        let mut tape = self.tape.borrow_mut();
+        let event = tape.transcript.events.get(tape.position).cloned()
+            .expect("Replayed program wants more than the transcript recorded.");
+        tape.position += 1;
+        event
+    }
+
+    pub fn input(&self, read: impl FnOnce() -> Option<String>) -> Option<String> {
+        match self.mode {
+            Mode::Replaying => match self.replay() {
+                Event::Input(line) => line,
+                event => panic!("Transcript has {:?} where the program reads input.", event)
+            },
+            _ => {
+                let line = read();
+                self.record(Event::Input(line.clone()));
+                line
+            }
+        }
+    }
+
+    pub fn sleep(&self, duration: Duration) -> Duration {
+        match self.mode {
+            Mode::Replaying => match self.replay() {
+                Event::Sleep(nanos) => Duration::from_nanos(nanos),
+                event => panic!("Transcript has {:?} where a humanoid pauses.", event)
+            },
+            _ => {
+                self.record(Event::Sleep(duration.as_nanos() as u64));
+                duration
+            }
+        }
+    }
+
+    pub fn cooldown_over(&self, check: impl FnOnce() -> bool) -> bool {
+        match self.mode {
+            Mode::Replaying => match self.replay() {
+                Event::Cooldown(over) => over,
+                event => panic!("Transcript has {:?} where the worker checks the time.", event)
+            },
+            _ => {
+                let over = check();
+                self.record(Event::Cooldown(over));
+                over
+            }
+        }
+    }
+}
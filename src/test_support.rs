@@ -0,0 +1,74 @@
+use crate::interpreter::{Interpreter, InterpreterBuilder, InterpreterError};
+use crate::lexer::Lexer;
+use crate::parser::{Parser, Value};
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+
+// Writer whose content can still be read after handing it to the interpreter.
+#[derive(Clone, Default)]
+pub struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub fn new() -> Self {
+        SharedBuffer::default()
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).to_string()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// Runs a program reading the given input, returns its result and everything it printed.
+pub fn run_captured(source: &str, input: &str) -> (Result<Value, InterpreterError>, String) {
+    run_captured_with(InterpreterBuilder::new(), source, input)
+}
+
+// Like run_captured, but lets the caller configure the interpreter (e.g. enable humanoids).
+pub fn run_captured_with(builder: InterpreterBuilder, source: &str, input: &str) -> (Result<Value, InterpreterError>, String) {
+    let output = SharedBuffer::new();
+    let result = captured(builder, source, input, &output).run();
+    (result, output.text())
+}
+
+// Like run_captured_with, but goes through interpret(), which also prints errors,
+// tracebacks, stats and the state dump.
+pub fn interpret_captured_with(builder: InterpreterBuilder, source: &str, input: &str) -> (Result<(), InterpreterError>, String) {
+    let output = SharedBuffer::new();
+    let result = captured(builder, source, input, &output).interpret();
+    (result, output.text())
+}
+
+fn captured(builder: InterpreterBuilder, source: &str, input: &str, output: &SharedBuffer) -> Interpreter {
+    builder
+        .seed(0)
+        .delays(false)
+        .input(Box::new(std::io::Cursor::new(input.to_string())))
+        .output(Box::new(output.clone()))
+        .build(Parser::new(Lexer::new(source)))
+}
+
+// Like run_captured, but keeps what went to :O__err apart, returns (result, stdout, stderr).
+pub fn run_captured_streams(source: &str, input: &str) -> (Result<Value, InterpreterError>, String, String) {
+    let output = SharedBuffer::new();
+    let error_output = SharedBuffer::new();
+    let mut interpreter = InterpreterBuilder::new()
+        .seed(0)
+        .delays(false)
+        .input(Box::new(std::io::Cursor::new(input.to_string())))
+        .output(Box::new(output.clone()))
+        .error_output(Box::new(error_output.clone()))
+        .build(Parser::new(Lexer::new(source)));
+    let result = interpreter.run();
+    (result, output.text(), error_output.text())
+}
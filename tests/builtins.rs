@@ -0,0 +1,37 @@
+use dmm::builtins::lookup;
+use dmm::interpreter::InterpreterError;
+use dmm::parser::Value;
+
+fn call(name: &str, arguments: &[Value]) -> Result<Value, InterpreterError> {
+    lookup(name).unwrap()(arguments)
+}
+
+fn string(text: &str) -> Value {
+    Value::String(text.to_string())
+}
+
+#[test]
+fn lists_survive_the_trip_through_text() {
+    let list = Value::List(vec![
+        string("a, b"), string("1"), string(":)"), string("-"), string("/x\\"), string("<>\\"),
+        Value::List(vec![Value::Integer(-3), Value::Float(2.5), Value::Boolean(false), Value::None]),
+        Value::List(vec![])
+    ]);
+    let text = call("liste_zu_text", std::slice::from_ref(&list)).unwrap();
+    assert_eq!(text, string(r"/<a, b>, <1>, <:)>, <->, </x\\>, <\<\>\\>, /-3, 2.5, :(, -\, /\\"));
+    assert_eq!(call("text_zu_liste", &[text]).unwrap(), list);
+}
+
+#[test]
+fn malformed_lists_are_parse_errors() {
+    for text in ["/1, 2", "/1, zwei\\", "/<offen\\", "/1\\ 2", "1, 2"] {
+        let result = call("text_zu_liste", &[string(text)]);
+        assert!(matches!(result, Err(InterpreterError::ParseError(_))), "{}: {:?}", text, result);
+    }
+}
+
+#[test]
+fn umdreh_reverses_characters_not_bytes() {
+    assert_eq!(call("umdreh", &[string("äöü")]).unwrap(), string("üöä"));
+    assert_eq!(call("umdreh", &[string("ab日本")]).unwrap(), string("本日ba"));
+}
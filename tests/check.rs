@@ -0,0 +1,29 @@
+use dmm::check::check_source;
+use serde_json::json;
+
+#[test]
+fn json_diagnostics_point_at_the_code() {
+    let source = "hallo\nunbenutzt = 1\nnirgends(2)\nreicht dann auch mal";
+    let diagnostics = serde_json::to_value(check_source(source)).unwrap();
+    assert_eq!(diagnostics, json!([
+        {"kind": "error", "message": "function `nirgends` is called but never declared", "position": {"line": 3, "column": 1}},
+        {"kind": "warning", "message": "variable `unbenutzt` is assigned but never read", "position": {"line": 2, "column": 1}}
+    ]));
+}
+
+#[test]
+fn syntax_errors_are_the_only_diagnostic() {
+    let diagnostics = check_source("hallo\nx = 1 @\nreicht dann auch mal");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].to_string(), "error at 2:7: InvalidSyntax(\"No token for '@' at line 2, col 7\")");
+}
+
+#[test]
+fn positions_inside_functions() {
+    let source = "hallo\nfunny f(a) avo\n    machma b uf a\n    wirf a\ncado\nf(1)\nfunny laenge(x) avo\nwirf x\ncado\nreicht dann auch mal";
+    let diagnostics: Vec<String> = check_source(source).iter().map(ToString::to_string).collect();
+    assert_eq!(diagnostics, vec![
+        "error at 7:7: function `laenge` has the name of a built-in function",
+        "warning at 3:12: variable `b` is assigned but never read"
+    ]);
+}
@@ -0,0 +1,24 @@
+use std::process::{Command, Output};
+
+fn dmm(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_dmm")).args(args).output().unwrap()
+}
+
+#[test]
+fn check_reads_json_anywhere() {
+    for args in [["check", "--json", "tests/basic/diagnose.dmm"], ["check", "tests/basic/diagnose.dmm", "--json"]] {
+        let output = dmm(&args);
+        assert_eq!(output.status.code(), Some(1));
+        assert!(String::from_utf8_lossy(&output.stdout).starts_with("[{\"kind\":\"error\""), "{:?}", output);
+    }
+}
+
+#[test]
+fn check_without_a_readable_file_is_a_usage_error() {
+    let output = dmm(&["check"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Missing file to check"), "{:?}", output);
+    let output = dmm(&["check", "--json", "tests/basic/gibts_nicht.dmm"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Cannot read tests/basic/gibts_nicht.dmm"), "{:?}", output);
+}
@@ -0,0 +1,31 @@
+use dmm::format::NumberFormat;
+use dmm::interpreter::InterpreterBuilder;
+use dmm::parser::Value;
+use dmm::test_support::run_captured_with;
+
+#[test]
+fn floats_follow_the_precision() {
+    let number = Value::Float(12.34567);
+    assert_eq!(NumberFormat::default().format(&number), "12.34567");
+    assert_eq!(NumberFormat { precision: Some(2), ..NumberFormat::default() }.format(&number), "12.35");
+    assert_eq!(NumberFormat { precision: Some(0), ..NumberFormat::default() }.format(&number), "12");
+}
+
+#[test]
+fn thousands_get_separated() {
+    let format = NumberFormat { precision: Some(1), thousands_separator: Some('\'') };
+    assert_eq!(format.format(&Value::Integer(-1234567)), "-1'234'567");
+    assert_eq!(format.format(&Value::Float(1234.56)), "1'234.6");
+    assert_eq!(format.format(&Value::List(vec![Value::Integer(999), Value::Integer(1000)])), "/999, 1'000\\");
+    assert_eq!(NumberFormat::default().format(&Value::Integer(1234567)), "1234567");
+}
+
+#[test]
+fn output_functions_use_the_number_format() {
+    let format = NumberFormat { precision: Some(3), thousands_separator: Some(' ') };
+    let source = "hallo\n:O__(10000.0 / 3, < >, 4096 * 4)\nreicht dann auch mal";
+    let (_, output) = run_captured_with(InterpreterBuilder::new().number_format(format), source, "");
+    assert_eq!(output, "3 333.333 16 384\n");
+    let (_, output) = run_captured_with(InterpreterBuilder::new(), source, "");
+    assert_eq!(output, "3333.3333333333335 16384\n");
+}
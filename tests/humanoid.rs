@@ -0,0 +1,127 @@
+use dmm::console::Console;
+use dmm::humanoid::{delay, HumanoidControl, HumanoidSettings, Language, Mood, Worker};
+use dmm::interpreter::{InterpreterBuilder, InterpreterError, Scope};
+use dmm::parser::{ASTNode, Value};
+use dmm::session::Session;
+use dmm::test_support::{interpret_captured_with, run_captured_with, SharedBuffer};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::io::Cursor;
+use std::rc::Rc;
+use std::time::Duration;
+
+// Wears a worker out until it quizzes the user about node, answered with answer.
+fn quiz(node: &ASTNode, correct: &Value, scope: &Scope, globals: &Scope, answer: &str) -> (Result<(), InterpreterError>, String) {
+    let output = SharedBuffer::new();
+    let mut console = Console::new(Box::new(Cursor::new(answer.to_string())), Box::new(output.clone()), Box::new(std::io::sink()), false, Session::live(), None);
+    let settings = HumanoidSettings { delays: false, ..HumanoidSettings::default() };
+    let mut worker = Worker::new(settings, StdRng::seed_from_u64(0), Session::live());
+    // Plain values are too boring to ask about, they only add stress.
+    let value = ASTNode::Value {value: Value::None};
+    while HumanoidControl::mood(&worker) != Mood::Deactivated {
+        worker.call(&mut console, scope, globals, &value, &Value::None).unwrap();
+    }
+    let result = worker.call(&mut console, scope, globals, node, correct);
+    (result, output.text())
+}
+
+fn variable(name: &str) -> ASTNode {
+    ASTNode::Variable {name: name.to_string(), offset: 0}
+}
+
+#[test]
+fn delay_is_zero_without_an_audience() {
+    assert_eq!(delay(false, Duration::from_millis(800)), Duration::ZERO);
+    assert_eq!(delay(true, Duration::from_millis(800)), Duration::from_millis(800));
+}
+
+#[test]
+fn manager_reviews_the_nesting_first() {
+    let source = std::fs::read_to_string("tests/basic/manager.dmm").unwrap();
+    let (_, output) = run_captured_with(InterpreterBuilder::new().humanoids(true), &source, "");
+    assert!(output.starts_with("[ Manager | Tiefe 7 ] Schön übersichtlich, weiter so!\n"), "{}", output);
+    // Block, 8 times If and Block, FunctionCall, Value.
+    let deep = format!("hallo\n{}:O__(1){}\nreicht dann auch mal", "wenn :) avo\n".repeat(8), "\ncado".repeat(8));
+    let (_, output) = run_captured_with(InterpreterBuilder::new().humanoids(true).language(Language::English), &deep, "");
+    assert!(output.starts_with("[ Manager | Depth 19 ] Nobody understands this anymore! Who is going to maintain it?\n"), "{}", output);
+    // Strict work has no manager.
+    let (_, output) = run_captured_with(InterpreterBuilder::new(), &source, "");
+    assert_eq!(output, "eins\n");
+}
+
+#[test]
+fn quiz_takes_numbers_by_value() {
+    let (result, output) = quiz(&variable("x"), &Value::Integer(1), &Scope::new(), &Scope::new(), "1.0\n");
+    assert!(result.is_ok(), "{:?}", result);
+    assert!(output.ends_with("Danke, du hast recht!\n"), "{}", output);
+    let (result, _) = quiz(&variable("x"), &Value::Integer(1), &Scope::new(), &Scope::new(), "1.5\n");
+    assert!(matches!(result, Err(InterpreterError::DisturbedWorker)), "{:?}", result);
+}
+
+#[test]
+fn quiz_takes_lists_as_answers() {
+    let correct = Value::List(vec![Value::Integer(1), Value::List(vec![Value::String("a".to_string())])]);
+    let (result, output) = quiz(&variable("x"), &correct, &Scope::new(), &Scope::new(), "/1, /<a>\\\\\n");
+    assert!(result.is_ok(), "{:?}", result);
+    assert!(output.ends_with("Danke, du hast recht!\n"), "{}", output);
+}
+
+#[test]
+fn quiz_skips_values_nobody_can_type() {
+    let function = Value::Function(Rc::new(ASTNode::Block {children: vec![]}));
+    let correct = Value::List(vec![Value::Integer(1), function]);
+    let (result, output) = quiz(&variable("x"), &correct, &Scope::new(), &Scope::new(), "");
+    assert!(result.is_ok(), "{:?}", result);
+    assert!(!output.contains(">>"), "{}", output);
+}
+
+// The last line interpret() prints with humanoid stats on.
+fn stats(source: &str) -> String {
+    let (result, output) = interpret_captured_with(InterpreterBuilder::new().humanoids(true).humanoid_stats(true), source, "");
+    result.unwrap();
+    output.lines().last().unwrap().to_string()
+}
+
+#[test]
+fn comparisons_stress_the_worker() {
+    let plain = stats("hallo\na = 1\nb = 2\nreicht dann auch mal");
+    let compared = stats("hallo\na = 1 kleina 2\nb = 1 is 1 und 2 krasser 1\nreicht dann auch mal");
+    assert_eq!(plain, "[ =D stress: 25 | =D voice damage: 0 ]");
+    // Same seed, the extra stress comes from visiting the comparisons.
+    assert_eq!(compared, "[ =) stress: 66 | =D voice damage: 0 ]");
+}
+
+fn describe(strict_work: bool, language: Language, error: &InterpreterError) -> String {
+    let settings = HumanoidSettings { strict_work, language, ..HumanoidSettings::default() };
+    Worker::new(settings, StdRng::seed_from_u64(0), Session::live()).describe_error(error)
+}
+
+#[test]
+fn errors_are_plain_in_strict_work() {
+    assert_eq!(describe(true, Language::Deutsch, &InterpreterError::DivisionByZero), "Oh oh... Durch 0 teilen geht nicht.");
+    assert_eq!(describe(true, Language::English, &InterpreterError::DivisionByZero), "Oh oh... Cannot divide by 0.");
+    assert_eq!(describe(true, Language::English, &InterpreterError::UserError("boom".to_string())), "Fehler: boom");
+}
+
+#[test]
+fn errors_are_in_character_otherwise() {
+    // A relaxed worker doesn't raise its voice yet.
+    assert_eq!(describe(false, Language::Deutsch, &InterpreterError::DivisionByZero), "=D Durch 0? Das kann ja keiner! (DivisionByZero)");
+    assert_eq!(describe(false, Language::English, &InterpreterError::UserError("boom".to_string())), "=D You asked for it! (boom)");
+}
+
+#[test]
+fn quiz_answers_may_name_variables() {
+    let mut globals = Scope::new();
+    globals.symbol_table.insert("zaehler".to_string(), Value::Integer(5));
+    let mut scope = Scope::new();
+    scope.symbol_table.insert("n".to_string(), Value::Integer(3));
+    scope.symbol_table.insert("zaehler".to_string(), Value::Integer(7));
+    let (result, _) = quiz(&variable("x"), &Value::Integer(3), &scope, &globals, "n\n");
+    assert!(result.is_ok(), "{:?}", result);
+    // Inside a function, globals are readable too, unless a local shadows them.
+    let (result, _) = quiz(&variable("x"), &Value::Integer(5), &Scope::new(), &globals, "zaehler\n");
+    assert!(result.is_ok(), "{:?}", result);
+    let (result, _) = quiz(&variable("x"), &Value::Integer(5), &scope, &globals, "zaehler\n");
+    assert!(matches!(result, Err(InterpreterError::DisturbedWorker)), "{:?}", result);
+}
@@ -0,0 +1,218 @@
+use dmm::humanoid::Language;
+use dmm::interpreter::{run_snippet, InterpreterBuilder, InterpreterError};
+use dmm::lexer::{Lexer, SNIPPET_VARIABLE};
+use dmm::parser::{Parser, Value};
+use dmm::test_support::{interpret_captured_with, run_captured, run_captured_streams, run_captured_with, SharedBuffer};
+use std::io::Cursor;
+
+#[test]
+fn builder_without_humanoids_runs_plainly() {
+    // Long enough that a worker would have lost it and a shouter would be hoarse.
+    let source = "hallo\ni = 0\nschleif i kleina 200 avo\n:O_____(<ruhig>)\ni = i + 1\ncado\nwirf i\nreicht dann auch mal";
+    let output = SharedBuffer::new();
+    let mut interpreter = InterpreterBuilder::new()
+        .humanoids(false)
+        .seed(7)
+        .language(Language::English)
+        .step_limit(100000)
+        .output(Box::new(output.clone()))
+        .build(Parser::new(Lexer::new(source)));
+    assert_eq!(interpreter.run().unwrap(), Value::Integer(200));
+    assert_eq!(output.text(), "ruhig\n".repeat(200));
+}
+
+#[test]
+fn endless_recursion_fails_with_stack_overflow() {
+    let source = "hallo\nfunny f(n) avo\nwirf f(n + 1)\ncado\nf(0)\nreicht dann auch mal";
+    let (result, _) = run_captured(source, "");
+    assert!(matches!(result, Err(InterpreterError::StackOverflow(1000))), "{:?}", result);
+}
+
+#[test]
+fn call_depth_limit_is_configurable() {
+    let source = "hallo\nfunny f(n) avo\nwenn n is 0 avo\nwirf tiefe()\ncado\nwirf f(n - 1)\ncado\nwirf f(20)\nreicht dann auch mal";
+    let (result, _) = run_captured_with(InterpreterBuilder::new().call_depth_limit(Some(10)), source, "");
+    assert!(matches!(result, Err(InterpreterError::StackOverflow(10))), "{:?}", result);
+    let (result, _) = run_captured_with(InterpreterBuilder::new().call_depth_limit(None), source, "");
+    assert_eq!(result.unwrap().to_string(), "21");
+}
+
+#[test]
+fn snippets_evaluate_bare_expressions() {
+    assert_eq!(run_snippet("2 + 2").unwrap(), Value::Integer(4));
+    assert_eq!(run_snippet("laenge(<abc>) * 2").unwrap(), Value::Integer(6));
+}
+
+#[test]
+fn snippets_keep_their_result_apart_from_program_variables() {
+    assert!(Lexer::new(SNIPPET_VARIABLE).get_next_token().is_err());
+    assert!(matches!(run_snippet("x"), Err(InterpreterError::UnknownVariable(name)) if name == "x"));
+}
+
+#[test]
+fn echo_input_repeats_what_was_read() {
+    let source = "hallo\nn = d;D(<Zahl>)\n:O__(n * 2)\nreicht dann auch mal";
+    let (_, output) = run_captured_with(InterpreterBuilder::new().echo_input(true), source, "7\n");
+    assert_eq!(output, "Zahl: 7\n14\n");
+    let (_, output) = run_captured(source, "7\n");
+    assert_eq!(output, "Zahl: 14\n");
+}
+
+#[test]
+fn silent_stats_accumulate_without_disturbing_the_output() {
+    let source = "hallo\n:O__(<eins>)\n:O___(1 + 1)\nreicht dann auch mal";
+    for seed in [1, 2] {
+        let output = SharedBuffer::new();
+        let mut interpreter = InterpreterBuilder::new()
+            .humanoid_stats(true)
+            .seed(seed)
+            .output(Box::new(output.clone()))
+            .build(Parser::new(Lexer::new(source)));
+        interpreter.interpret().unwrap();
+        assert_eq!(output.text(), "eins\n2\n[ =D stress: 30 | =D voice damage: 3 ]\n");
+    }
+}
+
+#[test]
+fn programs_read_their_arguments() {
+    let builder = InterpreterBuilder::new().args(vec!["eins".to_string(), "--zwei".to_string()]);
+    let (result, _) = run_captured_with(builder, "hallo\nwirf args()\nreicht dann auch mal", "");
+    assert_eq!(result.unwrap(), Value::List(vec![Value::String("eins".to_string()), Value::String("--zwei".to_string())]));
+}
+
+#[test]
+fn programs_can_come_from_any_reader() {
+    let run = |source: &str, envelope: bool| {
+        let output = SharedBuffer::new();
+        let mut interpreter = InterpreterBuilder::new()
+            .seed(0)
+            .delays(false)
+            .output(Box::new(output.clone()))
+            .build_from_reader(Cursor::new(source.to_string()), envelope)
+            .unwrap();
+        (interpreter.run(), output.text())
+    };
+    let (result, output) = run("hallo\n:O__(<gelesen>)\nreicht dann auch mal\n", false);
+    assert_eq!((result.unwrap(), output.as_str()), (Value::None, "gelesen\n"));
+    let (result, output) = run("a = 20\n:O__(a + 1)\n", true);
+    assert_eq!((result.unwrap(), output.as_str()), (Value::None, "21\n"));
+    // Without the envelope the greeting is missing.
+    let (result, _) = run("a = 20\n", false);
+    assert!(matches!(result, Err(InterpreterError::Syntax(_))), "{:?}", result);
+}
+
+#[test]
+fn environment_variables_need_environment_access() {
+    std::env::set_var("DMM_TEST_GRUSS", "servus");
+    let source = "hallo\nwirf /umgebung(<DMM_TEST_GRUSS>), umgebung(<DMM_TEST_GIBTS_NICHT>)\\\nreicht dann auch mal";
+    let (result, _) = run_captured_with(InterpreterBuilder::new().environment_access(true), source, "");
+    assert_eq!(result.unwrap(), Value::List(vec![Value::String("servus".to_string()), Value::None]));
+    let (result, _) = run_captured(source, "");
+    assert!(matches!(result, Err(InterpreterError::NotAllowed(_))), "{:?}", result);
+}
+
+#[test]
+fn large_factorials_overflow_plain_integers() {
+    let source = std::fs::read_to_string("tests/basic/fakultaet.dmm").unwrap();
+    let (result, output) = run_captured(&source, "");
+    assert!(matches!(result, Err(InterpreterError::IntegerOverflow)), "{:?}", result);
+    assert_eq!(output, "");
+    let (result, _) = run_captured("hallo\nwirf fakultaet(25)\nreicht dann auch mal", "");
+    assert!(matches!(result, Err(InterpreterError::IntegerOverflow)), "{:?}", result);
+}
+
+#[cfg(feature = "bignum")]
+#[test]
+fn big_integers_hold_large_factorials() {
+    let source = std::fs::read_to_string("tests/basic/fakultaet.dmm").unwrap();
+    let (result, output) = run_captured_with(InterpreterBuilder::new().big_integers(true), &source, "");
+    assert_eq!(result.unwrap(), Value::None);
+    assert_eq!(output, "15511210043330985984000000\n:)\n:)\n");
+    let (_, output) = run_captured_with(InterpreterBuilder::new().big_integers(true), "hallo\n:O__(fakultaet(25))\nreicht dann auch mal", "");
+    assert_eq!(output, "15511210043330985984000000\n");
+}
+
+#[test]
+fn dump_state_prints_the_globals_as_json() {
+    let source = "hallo\nzahl = 42\nkomma = 2.5\nliste = /1, <a>\\\nfunny f() avo\nlokal = 1\ncado\nf()\nreicht dann auch mal";
+    let (result, output) = interpret_captured_with(InterpreterBuilder::new().dump_state(true), source, "");
+    result.unwrap();
+    // Locals of f are gone by then.
+    assert_eq!(output, "{\"komma\":2.5,\"liste\":[1,\"a\"],\"zahl\":42}\n");
+}
+
+#[test]
+fn traceback_names_the_failing_functions() {
+    let source = std::fs::read_to_string("tests/basic/traceback.dmm").unwrap();
+    let (result, output) = interpret_captured_with(InterpreterBuilder::new().traceback(true), &source, "");
+    assert!(matches!(result, Err(InterpreterError::IndexOutOfRange {..})), "{:?}", result);
+    assert!(output.ends_with("Traceback (most recent call last):\n    aussen\n    innen\n"), "{}", output);
+    let (_, output) = interpret_captured_with(InterpreterBuilder::new(), &source, "");
+    assert!(!output.contains("Traceback"), "{}", output);
+}
+
+#[test]
+fn block_scoping_drops_variables_first_assigned_in_a_body() {
+    let source = std::fs::read_to_string("tests/basic/block_scope.dmm").unwrap();
+    let (result, output) = run_captured_with(InterpreterBuilder::new().block_scoping(true), &source, "");
+    assert!(matches!(result, Err(InterpreterError::UnknownVariable(name)) if name == "y"));
+    // Outer variables are still assigned from inside.
+    assert_eq!(output, "2\n");
+    let (result, output) = run_captured(&source, "");
+    assert_eq!((result.unwrap(), output.as_str()), (Value::None, "2\n3\n"));
+}
+
+#[test]
+fn error_output_stays_apart_from_the_output() {
+    let source = std::fs::read_to_string("tests/basic/fehler_aus.dmm").unwrap();
+    let (result, output, error_output) = run_captured_streams(&source, "");
+    assert_eq!(result.unwrap(), Value::None);
+    assert_eq!(output, "auf stdout\nwieder auf stdout\n");
+    assert_eq!(error_output, "auf stderr: 2\n");
+}
+
+#[test]
+fn answers_come_from_a_file() {
+    let path = std::env::temp_dir().join(format!("dmm-answers-{}.txt", std::process::id()));
+    std::fs::write(&path, "<Avo>\n20\n").unwrap();
+    let source = "hallo\nname = d;D(<Name>)\nalter = d;D(<Alter>)\n:O__(name, < >, alter + 1)\nreicht dann auch mal";
+    let output = SharedBuffer::new();
+    let mut interpreter = InterpreterBuilder::new()
+        .humanoids(true)
+        .seed(0)
+        .delays(false)
+        .answers_file(&path)
+        .unwrap()
+        .output(Box::new(output.clone()))
+        .build(Parser::new(Lexer::new(source)));
+    let result = interpreter.interpret();
+    std::fs::remove_file(&path).unwrap();
+    result.unwrap();
+    assert_eq!(output.text(), "[ Manager | Tiefe 4 ] Schön übersichtlich, weiter so!\nName: Alter: [ =) ]\nAvo 21\n");
+}
+
+#[test]
+fn endless_loops_stop_at_the_loop_limit() {
+    let source = "hallo\ni = 0\nschleif :) avo\n:O__(i)\ni = i + 1\ncado\nreicht dann auch mal";
+    let (result, output) = run_captured_with(InterpreterBuilder::new().loop_limit(3), source, "");
+    assert!(matches!(result, Err(InterpreterError::LoopLimitExceeded(3))), "{:?}", result);
+    assert_eq!(output, "0\n1\n2\n");
+}
+
+#[test]
+fn output_stops_at_the_output_limit() {
+    let source = "hallo\ni = 0\nschleif i kleina 1000 avo\n:O__(i)\ni = i + 1\ncado\nreicht dann auch mal";
+    let (result, output) = run_captured_with(InterpreterBuilder::new().output_limit(20), source, "");
+    assert!(matches!(result, Err(InterpreterError::OutputLimitExceeded(20))), "{:?}", result);
+    // 0 to 9 take exactly 20 bytes, 10 doesn't fit anymore.
+    let expected: String = (0..10).map(|i| format!("{}\n", i)).collect();
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn und_and_oda_skip_the_right_side_once_decided() {
+    // fehler() would fail if it ran.
+    assert_eq!(run_snippet(":( und fehler(<nie>)").unwrap(), Value::Boolean(false));
+    assert_eq!(run_snippet(":) oda fehler(<nie>)").unwrap(), Value::Boolean(true));
+    assert!(matches!(run_snippet(":) und fehler(<jetzt>)"), Err(InterpreterError::UserError(message)) if message == "jetzt"));
+}
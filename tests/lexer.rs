@@ -0,0 +1,10 @@
+use dmm::lexer::{Lexer, Token};
+
+#[test]
+fn empty_and_blank_input_is_just_eof() {
+    for text in ["", "   "] {
+        let mut lexer = Lexer::new(text);
+        assert_eq!(lexer.get_next_token().unwrap(), Token::EOF);
+        assert_eq!(lexer.get_next_token().unwrap(), Token::EOF);
+    }
+}
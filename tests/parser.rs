@@ -0,0 +1,98 @@
+use dmm::lexer::{Lexer, Token};
+use dmm::parser::{fold_constants, transform, ASTNode, CompareType, LogicType, Parser, Pass, Value};
+use std::rc::Rc;
+
+fn integer(value: i32) -> ASTNode {
+    ASTNode::Value {value: Value::Integer(value)}
+}
+
+fn variable(name: &str, offset: usize) -> ASTNode {
+    ASTNode::Variable {name: name.to_string(), offset}
+}
+
+fn bin_op(left: ASTNode, token: Token, right: ASTNode) -> ASTNode {
+    ASTNode::BinOp {left: Rc::new(left), right: Rc::new(right), token}
+}
+
+#[test]
+fn parses_a_lone_expression() {
+    let tree = Parser::new(Lexer::new("1 + 2 * x")).parse_expression().unwrap();
+    assert_eq!(tree, bin_op(integer(1), Token::Plus, bin_op(integer(2), Token::Multiply, variable("x", 8))));
+}
+
+#[test]
+fn parses_a_lone_statement() {
+    let tree = Parser::new(Lexer::new("machma x uf 1")).parse_statement().unwrap();
+    assert_eq!(tree, ASTNode::Assign {left: Rc::new(variable("x", 7)), right: Rc::new(integer(1))});
+}
+
+#[test]
+fn lone_fragments_must_be_complete() {
+    assert!(Parser::new(Lexer::new("1 + 2 3")).parse_expression().is_err());
+    assert!(Parser::new(Lexer::new("x = 1\ny = 2")).parse_statement().is_err());
+}
+
+fn double_literals(node: ASTNode) -> ASTNode {
+    match node {
+        ASTNode::Value {value: Value::Integer(value)} => integer(value * 2),
+        node => node
+    }
+}
+
+#[test]
+fn transform_applies_passes_in_order() {
+    let tree = || Parser::new(Lexer::new("1 + 2 * 3")).parse_expression().unwrap();
+    let double_then_fold: Vec<Pass> = vec![Box::new(double_literals), Box::new(fold_constants)];
+    assert_eq!(transform(tree(), &double_then_fold), integer(26));
+    let fold_then_double: Vec<Pass> = vec![Box::new(fold_constants), Box::new(double_literals)];
+    assert_eq!(transform(tree(), &fold_then_double), integer(14));
+}
+
+#[test]
+fn fold_constants_leaves_overflow_to_the_interpreter() {
+    let tree = Parser::new(Lexer::new("2147483647 + 1")).parse_expression().unwrap();
+    assert_eq!(transform(tree.clone(), &[Box::new(fold_constants)]), tree);
+}
+
+#[test]
+fn height_counts_the_deepest_nesting() {
+    assert_eq!(integer(1).height(), 1);
+    assert_eq!(Parser::new(Lexer::new("1 + 2 * x")).parse_expression().unwrap().height(), 3);
+    let source = std::fs::read_to_string("tests/basic/manager.dmm").unwrap();
+    assert_eq!(Parser::new(Lexer::new(&source)).parse().unwrap().height(), 7);
+}
+
+#[test]
+fn comments_stay_with_their_statements_when_asked() {
+    let source = "hallo\n# Eins\nx = 1\n\n# Zwei\ny = 2\n# Ende\nreicht dann auch mal # Schluss";
+    let assign = |name: &str, offset: usize, value: i32| ASTNode::Assign {left: Rc::new(variable(name, offset)), right: Rc::new(integer(value))};
+    let tree = Parser::with_comments(Lexer::new(source)).parse().unwrap();
+    assert_eq!(tree, ASTNode::Block {children: vec![
+        ASTNode::Commented {leading_comments: vec!["Eins".to_string()], statement: Rc::new(assign("x", 13, 1)), trailing_comments: vec![]},
+        ASTNode::Commented {
+            leading_comments: vec!["Zwei".to_string()],
+            statement: Rc::new(assign("y", 27, 2)),
+            trailing_comments: vec!["Ende".to_string(), "Schluss".to_string()]
+        }
+    ]});
+    let tree = Parser::new(Lexer::new(source)).parse().unwrap();
+    assert_eq!(tree, ASTNode::Block {children: vec![assign("x", 13, 1), assign("y", 27, 2)]});
+}
+
+fn logic(left: ASTNode, op: LogicType, right: ASTNode) -> ASTNode {
+    ASTNode::LogicOp {left: Rc::new(left), right: Rc::new(right), op}
+}
+
+#[test]
+fn und_binds_tighter_than_oda() {
+    let parse = |source| Parser::new(Lexer::new(source)).parse_expression().unwrap();
+    assert_eq!(parse("a und b oda c"), logic(logic(variable("a", 0), LogicType::And, variable("b", 6)), LogicType::Or, variable("c", 12)));
+    assert_eq!(parse("a oda b und c"), logic(variable("a", 0), LogicType::Or, logic(variable("b", 6), LogicType::And, variable("c", 12))));
+}
+
+#[test]
+fn comparisons_bind_tighter_than_und() {
+    let compare = |left, right| ASTNode::Compare {left: Rc::new(left), right: Rc::new(right), compare_type: CompareType::Equals};
+    let tree = Parser::new(Lexer::new("a is 1 und b is 2")).parse_expression().unwrap();
+    assert_eq!(tree, logic(compare(variable("a", 0), integer(1)), LogicType::And, compare(variable("b", 11), integer(2))));
+}
@@ -0,0 +1,26 @@
+use dmm::repl::{ReplAction, ReplBuffer};
+
+#[test]
+fn lines_run_right_away_until_buffering_starts() {
+    let mut buffer = ReplBuffer::new();
+    assert_eq!(buffer.handle(":O__(1)"), ReplAction::Execute(":O__(1)".to_string()));
+    assert_eq!(buffer.handle(":buffer"), ReplAction::BufferStarted);
+    assert_eq!(buffer.handle("x = 1"), ReplAction::Buffered);
+    assert_eq!(buffer.handle(":O__(x)"), ReplAction::Buffered);
+    assert_eq!(buffer.handle(":run"), ReplAction::Run("hallo\nx = 1\n:O__(x)\nreicht dann auch mal".to_string()));
+}
+
+#[test]
+fn run_keeps_the_buffer_and_clear_empties_it() {
+    let mut buffer = ReplBuffer::new();
+    buffer.handle(":buffer");
+    buffer.handle("x = 1");
+    buffer.handle(":run");
+    buffer.handle("y = 2");
+    assert_eq!(buffer.program(), "hallo\nx = 1\ny = 2\nreicht dann auch mal");
+    assert_eq!(buffer.handle(":clear"), ReplAction::Cleared);
+    assert_eq!(buffer.program(), "hallo\nreicht dann auch mal");
+    // Still collecting after clearing.
+    assert_eq!(buffer.handle("z = 3"), ReplAction::Buffered);
+    assert_eq!(buffer.handle(":run"), ReplAction::Run("hallo\nz = 3\nreicht dann auch mal".to_string()));
+}
@@ -0,0 +1,34 @@
+use dmm::test_support::run_captured;
+use std::fs;
+use std::path::Path;
+
+// Runs every sample in tests/basic and compares what it printed with tests/expected/<name>.txt.
+// The last line there is the program's result, `=> Ok(..)` or `=> Err(..)`.
+// Samples reading input get it from tests/answers/<name>.txt.
+#[test]
+fn samples_print_the_expected_output() {
+    let mut paths: Vec<_> = fs::read_dir("tests/basic").unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "dmm"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    let mut failures = Vec::new();
+    for path in paths {
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        let source = fs::read_to_string(&path).unwrap();
+        let input = fs::read_to_string(Path::new("tests/answers").join(format!("{}.txt", name))).unwrap_or_default();
+        let expected = fs::read_to_string(Path::new("tests/expected").join(format!("{}.txt", name)))
+            .unwrap_or_else(|_| panic!("No expected output for {}", name));
+        assert!(!expected.is_empty(), "Empty expected output for {}", name);
+        let (result, output) = run_captured(&source, &input);
+        // Prompts without a line break still get the result on a line of its own.
+        let separator = if output.is_empty() || output.ends_with('\n') { "" } else { "\n" };
+        let output = format!("{}{}=> {:?}\n", output, separator, result);
+        if output != expected {
+            failures.push(format!("{}:\n--- expected\n{}\n--- printed\n{}", name, expected, output));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
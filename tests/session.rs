@@ -0,0 +1,34 @@
+use dmm::interpreter::InterpreterBuilder;
+use dmm::lexer::Lexer;
+use dmm::parser::Parser;
+use dmm::session::{Event, Session};
+use dmm::test_support::SharedBuffer;
+use std::io::Cursor;
+
+// Reads input and keeps both humanoids busy, so the transcript has a bit of everything.
+const SOURCE: &str = "hallo\nn = d;D(<Wie oft>)\ni = 0\nschleif i kleina n avo\n:O__(i)\n:O_____(<laut>)\ni = i + 1\ncado\nreicht dann auch mal";
+
+fn run(session: Session, input: &str) -> (String, Session) {
+    let output = SharedBuffer::new();
+    let mut interpreter = InterpreterBuilder::new()
+        .humanoids(true)
+        .delays(false)
+        .session(session.clone())
+        .input(Box::new(Cursor::new(input.to_string())))
+        .output(Box::new(output.clone()))
+        .build(Parser::new(Lexer::new(SOURCE)));
+    let _ = interpreter.run();
+    (output.text(), session)
+}
+
+#[test]
+fn replaying_a_recording_prints_the_same() {
+    let (recorded, session) = run(Session::recording(), "40\n");
+    assert!(recorded.contains("39\n"), "{}", recorded);
+    let transcript = session.transcript();
+    assert_eq!(transcript.events.first(), Some(&Event::Input(Some("40\n".to_string()))));
+
+    // Neither the input nor a seed is given again, everything comes from the transcript.
+    let (replayed, _) = run(Session::replaying(transcript), "");
+    assert_eq!(replayed, recorded);
+}